@@ -0,0 +1,49 @@
+//! Perft throughput for the runtime-dispatched `BitBoard` and the monomorphized
+//! `BitBoard64` / `BitBoard256` representations.
+//!
+//! cargo run --release -p sf_core --example perft_bench [depth]
+
+use std::time::Instant;
+
+use sf_core::board::{BitBoard, BitBoard256, BitBoard64, Dimensions, BB};
+use sf_core::movegen::MoveGenerator;
+use sf_core::perft::perft;
+use sf_core::position::{Fen, Position};
+
+const STANDARD_FENS: [&str; 2] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+];
+
+const LARGE_FENS: [&str; 1] = [
+    "rnbqkbbnqr/pppppppppp/10/10/10/10/10/10/PPPPPPPPPP/RNBQKBBNQR w - - 0 1",
+];
+
+fn run<B: BB>(label: &str, dims: Dimensions, fens: &[&str], depth: u32) {
+    let gen: MoveGenerator<B> = MoveGenerator::new(dims);
+    let mut nodes = 0;
+    let start = Instant::now();
+
+    for fen in fens {
+        let mut pos: Position<B> = Fen::parse(fen, dims).unwrap();
+        nodes += perft(&mut pos, &gen, depth);
+    }
+
+    let secs = start.elapsed().as_secs_f64();
+    println!("{label:<20} {nodes:>10} nodes  {secs:>7.3}s  {:>12.0} nps", nodes as f64 / secs);
+}
+
+fn main() {
+    let depth = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(4);
+
+    let standard = Dimensions::standard();
+    run::<BitBoard>("8x8 BitBoard", standard, &STANDARD_FENS, depth);
+    run::<BitBoard64>("8x8 BitBoard64", standard, &STANDARD_FENS, depth);
+
+    let large = Dimensions::new(10, 10);
+    run::<BitBoard>("10x10 BitBoard", large, &LARGE_FENS, depth);
+    run::<BitBoard256>("10x10 BitBoard256", large, &LARGE_FENS, depth);
+}
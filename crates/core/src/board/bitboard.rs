@@ -1,19 +1,41 @@
 use super::square::Square;
 use super::dims::Dimensions;
 
-pub trait BB: Copy + Sized {
+/// Bitboard operations shared by every board size.
+///
+/// `Position`, `AttackTable` and `MoveGenerator` are generic over this trait, so
+/// the representation is picked once when they are built (see `AnyPosition`)
+/// instead of being matched on for every operation.
+pub trait BB: Copy + Sized + std::fmt::Debug + PartialEq {
+    /// Number of squares this representation can hold
+    const MAX_SQUARES: u16;
+    
     fn empty() -> Self;
+    
+    /// Empty bitboard suitable for a board of `dims`
+    #[inline]
+    fn empty_for_dims(_dims: &Dimensions) -> Self {
+        Self::empty()
+    }
+    
     fn full() -> Self;
     fn from_square(sq: Square) -> Self;
     fn clear(self, sq: Square) -> Self;
     fn set(self, sq: Square) -> Self;
     fn contains(self, sq: Square) -> bool;
     fn pop_lsb(&mut self) -> Option<Square>;
+    fn lsb(self) -> Option<Square>;
+    fn msb(self) -> Option<Square>;
     fn count(self) -> u32;
     fn is_empty(self) -> bool;
     fn union(self, other: Self) -> Self;
     fn intersect(self, other: Self) -> Self;
     fn difference(self, other: Self) -> Self;
+    fn xor(self, other: Self) -> Self;
+    
+    /// Two's complement subtraction over the whole representation, used by the
+    /// slider attacks in `AttackTable`
+    fn wrapping_sub(self, other: Self) -> Self;
 }
 
 /// BitBoard64 - u64 implementation for boards up to 64 squares
@@ -21,6 +43,8 @@ pub trait BB: Copy + Sized {
 pub struct BitBoard64(pub u64);
 
 impl BB for BitBoard64 {
+    const MAX_SQUARES: u16 = 64;
+    
     #[inline] 
     fn empty() -> Self { 
         BitBoard64(0) 
@@ -60,6 +84,22 @@ impl BB for BitBoard64 {
         self.0 &= self.0 - 1;
         Some(Square(lsb))
     }
+
+    #[inline]
+    fn lsb(self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        Some(Square(self.0.trailing_zeros() as u16))
+    }
+
+    #[inline]
+    fn msb(self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        Some(Square(63 - self.0.leading_zeros() as u16))
+    }
     
     #[inline]
     fn count(self) -> u32 {
@@ -85,6 +125,16 @@ impl BB for BitBoard64 {
     fn difference(self, other: Self) -> Self {
         BitBoard64(self.0 & !other.0)
     }
+    
+    #[inline]
+    fn xor(self, other: Self) -> Self {
+        BitBoard64(self.0 ^ other.0)
+    }
+    
+    #[inline]
+    fn wrapping_sub(self, other: Self) -> Self {
+        BitBoard64(self.0.wrapping_sub(other.0))
+    }
 }
 
 /// Simple U256 implementation using [u64; 4]
@@ -93,22 +143,22 @@ impl BB for BitBoard64 {
 pub(crate) struct U256([u64; 4]);
 
 impl U256 {
+    #[inline]
     fn zero() -> Self {
         U256([0, 0, 0, 0])
     }
     
+    #[inline]
     fn max() -> Self {
         U256([u64::MAX, u64::MAX, u64::MAX, u64::MAX])
     }
     
-    fn from_u64(val: u64) -> Self {
-        U256([val, 0, 0, 0])
-    }
-    
+    #[inline]
     fn is_zero(&self) -> bool {
         self.0.iter().all(|&w| w == 0)
     }
     
+    #[inline]
     fn shl(&self, bits: u16) -> Self {
         if bits >= 256 {
             return Self::zero();
@@ -117,36 +167,19 @@ impl U256 {
         let word_shift = (bits / 64) as usize;
         let bit_shift = bits % 64;
         
-        if word_shift >= 4 {
-            return Self::zero();
-        }
-        
+        // words are little-endian: word 0 holds squares 0..64
         let mut result = [0u64; 4];
-        
-        if bit_shift == 0 {
-            // word shift
-            for i in word_shift..4 {
-                result[i - word_shift] = self.0[i];
-            }
-        } else {
-            // bit shift across words
-            for i in word_shift..4 {
-                let src_idx = i;
-                let dst_idx = i - word_shift;
-                
-                if src_idx < 4 {
-                    result[dst_idx] |= self.0[src_idx] << bit_shift;
-                }
-                
-                if src_idx + 1 < 4 && bit_shift > 0 {
-                    result[dst_idx] |= self.0[src_idx + 1] >> (64 - bit_shift);
-                }
+        for (i, word) in result.iter_mut().enumerate().skip(word_shift) {
+            *word = self.0[i - word_shift] << bit_shift;
+            if bit_shift > 0 && i > word_shift {
+                *word |= self.0[i - word_shift - 1] >> (64 - bit_shift);
             }
         }
         
         U256(result)
     }
     
+    #[inline]
     fn bitwise_and(&self, other: &Self) -> Self {
         U256([
             self.0[0] & other.0[0],
@@ -156,6 +189,7 @@ impl U256 {
         ])
     }
     
+    #[inline]
     fn bitwise_or(&self, other: &Self) -> Self {
         U256([
             self.0[0] | other.0[0],
@@ -165,6 +199,30 @@ impl U256 {
         ])
     }
     
+    #[inline]
+    fn bitwise_xor(&self, other: &Self) -> Self {
+        U256([
+            self.0[0] ^ other.0[0],
+            self.0[1] ^ other.0[1],
+            self.0[2] ^ other.0[2],
+            self.0[3] ^ other.0[3],
+        ])
+    }
+    
+    #[inline]
+    fn wrapping_sub(&self, other: &Self) -> Self {
+        let mut result = [0u64; 4];
+        let mut borrow = false;
+        for (i, word) in result.iter_mut().enumerate() {
+            let (diff, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (diff, b2) = diff.overflowing_sub(borrow as u64);
+            *word = diff;
+            borrow = b1 || b2;
+        }
+        U256(result)
+    }
+    
+    #[inline]
     fn bitwise_not(&self) -> Self {
         U256([
             !self.0[0],
@@ -174,6 +232,7 @@ impl U256 {
         ])
     }
     
+    #[inline]
     fn trailing_zeros(&self) -> Option<u16> {
         for (word_idx, &word) in self.0.iter().enumerate() {
             if word != 0 {
@@ -182,7 +241,18 @@ impl U256 {
         }
         None
     }
+
+    #[inline]
+    fn leading_bit(&self) -> Option<u16> {
+        for (word_idx, &word) in self.0.iter().enumerate().rev() {
+            if word != 0 {
+                return Some((word_idx * 64) as u16 + 63 - word.leading_zeros() as u16);
+            }
+        }
+        None
+    }
     
+    #[inline]
     fn count_ones(&self) -> u32 {
         self.0.iter().map(|w| w.count_ones()).sum()
     }
@@ -190,6 +260,7 @@ impl U256 {
 
 impl std::ops::BitAnd for U256 {
     type Output = Self;
+    #[inline]
     fn bitand(self, other: Self) -> Self {
         self.bitwise_and(&other)
     }
@@ -197,6 +268,7 @@ impl std::ops::BitAnd for U256 {
 
 impl std::ops::BitOr for U256 {
     type Output = Self;
+    #[inline]
     fn bitor(self, other: Self) -> Self {
         self.bitwise_or(&other)
     }
@@ -204,6 +276,7 @@ impl std::ops::BitOr for U256 {
 
 impl std::ops::Not for U256 {
     type Output = Self;
+    #[inline]
     fn not(self) -> Self {
         self.bitwise_not()
     }
@@ -211,6 +284,7 @@ impl std::ops::Not for U256 {
 
 impl std::ops::Shl<u16> for U256 {
     type Output = Self;
+    #[inline]
     fn shl(self, bits: u16) -> Self {
         U256::shl(&self, bits)
     }
@@ -220,6 +294,8 @@ impl std::ops::Shl<u16> for U256 {
 pub struct BitBoard256(U256);
 
 impl BB for BitBoard256 {
+    const MAX_SQUARES: u16 = 256;
+    
     #[inline]
     fn empty() -> Self {
         BitBoard256(U256::zero())
@@ -232,27 +308,27 @@ impl BB for BitBoard256 {
     
     #[inline]
     fn from_square(sq: Square) -> Self {
-        let sq_idx = sq.0;
-        let one = U256::from_u64(1);
-        BitBoard256(one << sq_idx)
+        Self::empty().set(sq)
     }
     
     #[inline]
-    fn clear(self, sq: Square) -> Self {
-        let sq_bb = Self::from_square(sq);
-        BitBoard256(self.0 & !sq_bb.0)
+    fn clear(mut self, sq: Square) -> Self {
+        let idx = sq.0 as usize;
+        self.0 .0[idx / 64] &= !(1u64 << (idx % 64));
+        self
     }
     
     #[inline]
-    fn set(self, sq: Square) -> Self {
-        let sq_bb = Self::from_square(sq);
-        BitBoard256(self.0 | sq_bb.0)
+    fn set(mut self, sq: Square) -> Self {
+        let idx = sq.0 as usize;
+        self.0 .0[idx / 64] |= 1u64 << (idx % 64);
+        self
     }
     
     #[inline]
     fn contains(self, sq: Square) -> bool {
-        let sq_bb = Self::from_square(sq);
-        !(self.0 & sq_bb.0).is_zero()
+        let idx = sq.0 as usize;
+        (self.0 .0[idx / 64] >> (idx % 64)) & 1 == 1
     }
     
     #[inline]
@@ -261,14 +337,19 @@ impl BB for BitBoard256 {
             return None;
         }
         
-        if let Some(bit_pos) = self.0.trailing_zeros() {
-            // Clear the bit
-            let one = U256::from_u64(1);
-            let mask = !(one << bit_pos);
-            self.0 = self.0 & mask;
-            return Some(Square(bit_pos));
-        }
-        None
+        let sq = Square(self.0.trailing_zeros()?);
+        *self = self.clear(sq);
+        Some(sq)
+    }
+
+    #[inline]
+    fn lsb(self) -> Option<Square> {
+        self.0.trailing_zeros().map(Square)
+    }
+
+    #[inline]
+    fn msb(self) -> Option<Square> {
+        self.0.leading_bit().map(Square)
     }
     
     #[inline]
@@ -295,6 +376,16 @@ impl BB for BitBoard256 {
     fn difference(self, other: Self) -> Self {
         BitBoard256(self.0 & !other.0)
     }
+    
+    #[inline]
+    fn xor(self, other: Self) -> Self {
+        BitBoard256(self.0.bitwise_xor(&other.0))
+    }
+    
+    #[inline]
+    fn wrapping_sub(self, other: Self) -> Self {
+        BitBoard256(self.0.wrapping_sub(&other.0))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -326,11 +417,16 @@ impl BitBoard {
 }
 
 impl BB for BitBoard {
+    const MAX_SQUARES: u16 = 256;
+    
     fn empty() -> Self {
-
         BitBoard::Small(BitBoard64::empty())
     }
     
+    fn empty_for_dims(dims: &Dimensions) -> Self {
+        BitBoard::for_dims(dims)
+    }
+    
     fn full() -> Self {
         BitBoard::Small(BitBoard64::full())
     }
@@ -375,6 +471,20 @@ impl BB for BitBoard {
         }
     }
     
+    fn lsb(self) -> Option<Square> {
+        match self {
+            BitBoard::Small(bb) => bb.lsb(),
+            BitBoard::Large(bb) => bb.lsb(),
+        }
+    }
+
+    fn msb(self) -> Option<Square> {
+        match self {
+            BitBoard::Small(bb) => bb.msb(),
+            BitBoard::Large(bb) => bb.msb(),
+        }
+    }
+    
     fn count(self) -> u32 {
         match self {
             BitBoard::Small(bb) => bb.count(),
@@ -412,6 +522,22 @@ impl BB for BitBoard {
             _ => panic!("Cannot difference different bitboard types"),
         }
     }
+    
+    fn xor(self, other: Self) -> Self {
+        match (self, other) {
+            (BitBoard::Small(a), BitBoard::Small(b)) => BitBoard::Small(a.xor(b)),
+            (BitBoard::Large(a), BitBoard::Large(b)) => BitBoard::Large(a.xor(b)),
+            _ => panic!("Cannot xor different bitboard types"),
+        }
+    }
+    
+    fn wrapping_sub(self, other: Self) -> Self {
+        match (self, other) {
+            (BitBoard::Small(a), BitBoard::Small(b)) => BitBoard::Small(a.wrapping_sub(b)),
+            (BitBoard::Large(a), BitBoard::Large(b)) => BitBoard::Large(a.wrapping_sub(b)),
+            _ => panic!("Cannot subtract different bitboard types"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -461,4 +587,31 @@ mod tests {
         assert!(combined.contains(sq2));
         assert_eq!(combined.count(), 2);
     }
+    
+    #[test]
+    fn bitboard256_wrapping_sub() {
+        let dims = Dimensions::new(16, 16);
+        let low = Square::from_rank_file(0, 3, &dims);
+        let high = Square::from_rank_file(9, 2, &dims);
+        
+        // borrow runs from the high word down to the low one
+        let diff = BitBoard256::from_square(high).wrapping_sub(BitBoard256::from_square(low));
+        assert_eq!(diff.lsb(), Some(low));
+        assert_eq!(diff.msb(), Some(Square(high.0 - 1)));
+        assert_eq!(diff.count(), (high.0 - low.0) as u32);
+    }
+    
+    #[test]
+    fn bitboard256_high_squares() {
+        let dims = Dimensions::new(16, 16);
+        let top = Square::from_rank_file(15, 15, &dims);
+        let mid = Square::from_rank_file(4, 3, &dims);
+        
+        let bb = BitBoard::for_dims(&dims).set(top).set(mid);
+        assert!(bb.contains(top));
+        assert!(bb.contains(mid));
+        assert_eq!(bb.lsb(), Some(mid));
+        assert_eq!(bb.msb(), Some(top));
+        assert_eq!(bb.clear(top).count(), 1);
+    }
 }
@@ -1,15 +1,36 @@
+use crate::error::DimensionsError;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dimensions {
     pub width: u8,
     pub height: u8,
 }
 
 impl Dimensions {
+    pub const MIN_SIZE: u8 = 5;
+    pub const MAX_SIZE: u8 = 16;
+    
+    /// # Panics
+    /// If either side is outside `MIN_SIZE..=MAX_SIZE`; use `try_new` for untrusted sizes.
     pub fn new(width: u8, height: u8) -> Self {
-        assert!(width >= 5 && width <= 16, "Width must be between 5 and 16");
-        assert!(height >= 5 && height <= 16, "Height must be between 5 and 16");
-        Self { width, height }
+        Self::try_new(width, height).unwrap_or_else(|err| panic!("{err}"))
+    }
+    
+    pub fn try_new(width: u8, height: u8) -> Result<Self, DimensionsError> {
+        let dims = Self { width, height };
+        dims.validate()?;
+        Ok(dims)
+    }
+    
+    /// Check the size bounds, for values built from the public fields directly
+    pub fn validate(&self) -> Result<(), DimensionsError> {
+        let range = Self::MIN_SIZE..=Self::MAX_SIZE;
+        if range.contains(&self.width) && range.contains(&self.height) {
+            Ok(())
+        } else {
+            Err(DimensionsError::OutOfRange { width: self.width, height: self.height })
+        }
     }
     
     pub fn standard() -> Self {
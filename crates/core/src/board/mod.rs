@@ -3,6 +3,6 @@ pub mod square;
 pub mod bitboard;
 
 pub use dims::Dimensions;
-pub use square::Square;
-pub use bitboard::{BitBoard, BB};
+pub use square::{BoardSquare, Square};
+pub use bitboard::{BitBoard, BitBoard64, BitBoard256, BB};
 
@@ -1,6 +1,12 @@
+use std::fmt;
+
 use super::dims::Dimensions;
+use crate::notation::{self, NotationError};
 
+/// Square index, numbered rank by rank from the first file of the first rank. In
+/// JSON a square is its bare index.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct Square(pub u16);
 
 impl Square {
@@ -19,10 +25,32 @@ impl Square {
         dims.square_to_file_rank(self.0 as u8)
     }
 
+    /// Coordinate text such as `e4` or `a10`
     pub fn to_string(self, dims: &Dimensions) -> String {
-        let (rank, file) = self.file_rank(dims);
-        let file_char = (b'a' + file as u8) as char;
-        format!("{file_char}{}", rank + 1)
+        notation::format_square(self, dims)
+    }
+    
+    pub fn parse(text: &str, dims: &Dimensions) -> Result<Self, NotationError> {
+        notation::parse_square(text, dims)
+    }
+
+    /// This square together with its board, for messages
+    pub fn on(self, dims: Dimensions) -> BoardSquare {
+        BoardSquare { sq: self, dims }
+    }
+}
+
+/// A square with the board it lies on, so errors and problem reports can name it as
+/// `e4` while callers still get the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardSquare {
+    pub sq: Square,
+    pub dims: Dimensions,
+}
+
+impl fmt::Display for BoardSquare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.sq.to_string(&self.dims))
     }
 }
 
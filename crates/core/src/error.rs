@@ -0,0 +1,160 @@
+use std::fmt;
+
+use crate::{
+    board::{BoardSquare, Dimensions, Square},
+    moves::{Move, MoveType},
+    notation::{betza::BetzaError, pgn::PgnError, NotationError},
+    piece::{PieceKind, RegistryError},
+    position::FenError,
+};
+
+/// Any error returned by the fallible `sf_core` APIs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Betza(BetzaError),
+    Dimensions(DimensionsError),
+    Fen(FenError),
+    Move(MoveError),
+    Notation(NotationError),
+    Pgn(PgnError),
+    Registry(RegistryError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DimensionsError {
+    /// Width or height outside `Dimensions::MIN_SIZE..=Dimensions::MAX_SIZE`
+    OutOfRange { width: u8, height: u8 },
+    /// The board has more squares than the chosen bitboard can hold
+    TooManySquares { squares: u16, max: u16 },
+}
+
+/// Why a move cannot be applied to or taken back from a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// A square index past the end of the board, which has no coordinate to name it by
+    OffBoard(Square),
+    EmptySource(BoardSquare),
+    /// The piece on the source square belongs to the side not to move
+    NotSideToMove(BoardSquare),
+    /// The target holds a piece of the mover or a wall
+    BlockedTarget(BoardSquare),
+    /// The move type does not fit the position, e.g. en passant without an en passant square
+    KindMismatch(MoveType),
+    /// A drop of a piece the side to move does not hold
+    NotInPocket(PieceKind),
+    /// A capture with holdings into a pocket that already holds `u8::MAX` of the kind
+    PocketFull(PieceKind),
+    /// `unmake_move` was called with an empty history
+    NothingToUndo,
+    /// The move is not the one that was played last
+    NotLastMove { mv: Move, dims: Dimensions },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Betza(err) => err.fmt(f),
+            Error::Dimensions(err) => err.fmt(f),
+            Error::Fen(err) => err.fmt(f),
+            Error::Move(err) => err.fmt(f),
+            Error::Notation(err) => err.fmt(f),
+            Error::Pgn(err) => err.fmt(f),
+            Error::Registry(err) => err.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for DimensionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimensionsError::OutOfRange { width, height } => write!(
+                f,
+                "{width}x{height} board is out of range, width and height must be between {} and {}",
+                Dimensions::MIN_SIZE,
+                Dimensions::MAX_SIZE,
+            ),
+            DimensionsError::TooManySquares { squares, max } => {
+                write!(f, "board has {squares} squares but the bitboard holds at most {max}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::OffBoard(sq) => write!(f, "square {} is off the board", sq.0),
+            MoveError::EmptySource(sq) => write!(f, "no piece on source square {sq}"),
+            MoveError::NotSideToMove(sq) => {
+                write!(f, "piece on {sq} does not belong to the side to move")
+            }
+            MoveError::BlockedTarget(sq) => write!(f, "target square {sq} is blocked"),
+            MoveError::KindMismatch(kind) => write!(f, "{kind:?} move does not fit the position"),
+            MoveError::NotInPocket(kind) => write!(f, "no {kind:?} in the pocket to drop"),
+            MoveError::PocketFull(kind) => write!(f, "no room in the pocket for another {kind:?}"),
+            MoveError::NothingToUndo => write!(f, "no move to undo"),
+            MoveError::NotLastMove { mv, dims } => {
+                write!(f, "move {} was not the last move played", mv.to_uci(dims))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Betza(err) => Some(err),
+            Error::Dimensions(err) => Some(err),
+            Error::Fen(err) => Some(err),
+            Error::Move(err) => Some(err),
+            Error::Notation(err) => Some(err),
+            Error::Pgn(err) => Some(err),
+            Error::Registry(err) => Some(err),
+        }
+    }
+}
+
+impl std::error::Error for DimensionsError {}
+impl std::error::Error for MoveError {}
+
+impl From<DimensionsError> for Error {
+    fn from(err: DimensionsError) -> Self {
+        Error::Dimensions(err)
+    }
+}
+
+impl From<BetzaError> for Error {
+    fn from(err: BetzaError) -> Self {
+        Error::Betza(err)
+    }
+}
+
+impl From<FenError> for Error {
+    fn from(err: FenError) -> Self {
+        Error::Fen(err)
+    }
+}
+
+impl From<MoveError> for Error {
+    fn from(err: MoveError) -> Self {
+        Error::Move(err)
+    }
+}
+
+impl From<NotationError> for Error {
+    fn from(err: NotationError) -> Self {
+        Error::Notation(err)
+    }
+}
+
+impl From<PgnError> for Error {
+    fn from(err: PgnError) -> Self {
+        Error::Pgn(err)
+    }
+}
+
+impl From<RegistryError> for Error {
+    fn from(err: RegistryError) -> Self {
+        Error::Registry(err)
+    }
+}
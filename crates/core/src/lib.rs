@@ -4,9 +4,14 @@ pub mod position;
 pub mod moves;
 pub mod movegen;
 pub mod perft;
+pub mod error;
+pub mod notation;
+#[cfg(feature = "serde")]
+pub mod serialize;
 
 pub mod prelude {
-    pub use crate::board::{Dimensions, Square, BitBoard, BB};
+    pub use crate::board::{Dimensions, Square, BitBoard, BitBoard64, BitBoard256, BB};
+    pub use crate::error::Error;
 }
 
 #[cfg(test)]
@@ -20,11 +25,11 @@ mod integration_tests {
         let dims = Dimensions::standard();
         let fen_str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
         
-        assert!(moves.len() > 0);
+        assert!(!moves.is_empty());
         assert!(pos.piece_bb(Color::White, PieceKind::King).count() > 0);
         assert!(pos.piece_bb(Color::Black, PieceKind::King).count() > 0);
     }
@@ -1,125 +1,225 @@
 use crate::board::{Dimensions, Square, BitBoard, BB};
+use crate::movegen::patterns::Direction;
 
 
-pub struct AttackTable {
+/// Lines through a square, each excluding the square itself
+const FILE: usize = 0;
+const RANK: usize = 1;
+const DIAGONAL: usize = 2;
+const ANTI_DIAGONAL: usize = 3;
+
+/// Unit directions with the line they run along, in `rays` order. The first
+/// direction of each pair points towards higher square indices.
+const UNIT_DIRS: [(Direction, usize); 8] = [
+    (Direction::NORTH, FILE),
+    (Direction::SOUTH, FILE),
+    (Direction::EAST, RANK),
+    (Direction::WEST, RANK),
+    (Direction::NORTHEAST, DIAGONAL),
+    (Direction::SOUTHWEST, DIAGONAL),
+    (Direction::NORTHWEST, ANTI_DIAGONAL),
+    (Direction::SOUTHEAST, ANTI_DIAGONAL),
+];
+
+/// Precomputed leaper and slider attacks. Slider lookups take the squares that
+/// block movement, which for a `Position` is `Position::blockers` so walls stop rays.
+pub struct AttackTable<B = BitBoard> {
     dims: Dimensions,
-    knight_attacks: Vec<BitBoard>,
-    king_attacks: Vec<BitBoard>,
-    
-
-    rook_rays: Vec<Vec<BitBoard>>,  // [square][direction] -> ray bitboard
-    bishop_rays: Vec<Vec<BitBoard>>, // [square][direction] -> ray bitboard
-    
-    // Direction indices
-    rook_dir_count: usize,
-    bishop_dir_count: usize,
+    knight_attacks: Vec<B>,
+    king_attacks: Vec<B>,
+
+    first_square: B,
+    lines: Vec<[B; 4]>, // [square][line] -> line mask without the square
+    rays: Vec<[B; 8]>,  // [square][unit direction] -> ray mask
+
+    /// Squares on files 0..=f and ranks 0..=r, for distance limits
+    files_upto: Vec<B>,
+    ranks_upto: Vec<B>,
 }
 
-impl AttackTable {
+impl<B: BB> AttackTable<B> {
     pub fn new(dims: Dimensions) -> Self {
-        
+
         let knight_attacks = Self::precompute_knight_attacks(dims);
         let king_attacks = Self::precompute_king_attacks(dims);
-        
-        let (rook_rays, rook_dir_count) = Self::precompute_rook_rays(dims);
-        let (bishop_rays, bishop_dir_count) = Self::precompute_bishop_rays(dims);
-        
+
+        let rays = Self::precompute_rays(dims);
+        let lines = rays
+            .iter()
+            .map(|rays| {
+                let mut lines = [B::empty_for_dims(&dims); 4];
+                for (dir_idx, &(_, line)) in UNIT_DIRS.iter().enumerate() {
+                    lines[line] = lines[line].union(rays[dir_idx]);
+                }
+                lines
+            })
+            .collect();
+
+        let (files_upto, ranks_upto) = Self::precompute_prefix_masks(dims);
+
         Self {
             dims,
             knight_attacks,
             king_attacks,
-            rook_rays,
-            bishop_rays,
-            rook_dir_count,
-            bishop_dir_count,
+            first_square: B::empty_for_dims(&dims).set(Square(0)),
+            lines,
+            rays,
+            files_upto,
+            ranks_upto,
         }
     }
-    
+
     #[inline]
-    pub fn knight_attacks(&self, sq: Square) -> BitBoard {
+    pub fn dims(&self) -> &Dimensions {
+        &self.dims
+    }
+
+    #[inline]
+    pub fn knight_attacks(&self, sq: Square) -> B {
         self.knight_attacks[sq.idx() as usize]
     }
-    
+
     #[inline]
-    pub fn king_attacks(&self, sq: Square) -> BitBoard {
+    pub fn king_attacks(&self, sq: Square) -> B {
         self.king_attacks[sq.idx() as usize]
     }
-    
-    pub fn rook_attacks(&self, sq: Square, occupied: BitBoard) -> BitBoard {
+
+    #[inline]
+    pub fn rook_attacks(&self, sq: Square, occupied: B) -> B {
+        self.line_attacks(sq, occupied, FILE)
+            .union(self.line_attacks(sq, occupied, RANK))
+    }
+
+    #[inline]
+    pub fn bishop_attacks(&self, sq: Square, occupied: B) -> B {
+        self.line_attacks(sq, occupied, DIAGONAL)
+            .union(self.line_attacks(sq, occupied, ANTI_DIAGONAL))
+    }
+
+    /// Get queen attacks (rook + bishop)
+    pub fn queen_attacks(&self, sq: Square, occupied: B) -> B {
+        self.rook_attacks(sq, occupied).union(self.bishop_attacks(sq, occupied))
+    }
+
+    /// Get archbishop attacks (knight + bishop)
+    pub fn archbishop_attacks(&self, sq: Square, occupied: B) -> B {
+        self.knight_attacks(sq).union(self.bishop_attacks(sq, occupied))
+    }
+
+    /// Get chancellor attacks (knight + rook)
+    pub fn chancellor_attacks(&self, sq: Square, occupied: B) -> B {
+        self.knight_attacks(sq).union(self.rook_attacks(sq, occupied))
+    }
+
+    /// Get amazon attacks (knight + queen)
+    pub fn amazon_attacks(&self, sq: Square, occupied: B) -> B {
+        self.knight_attacks(sq).union(self.queen_attacks(sq, occupied))
+    }
+
+    /// Attacks of a slider restricted to `directions`, optionally limited to
+    /// `max_distance` steps. Returns `None` if a direction is not a unit step,
+    /// in which case the caller has to walk the board itself.
+    pub fn slider_attacks(
+        &self,
+        sq: Square,
+        occupied: B,
+        directions: &[Direction],
+        max_distance: Option<u8>,
+    ) -> Option<B> {
         let sq_idx = sq.idx() as usize;
-        let mut attacks = BitBoard::empty_for_dims(&self.dims);
-        
-        // For each direction, find the first blocker and mask the ray
-        for dir_idx in 0..self.rook_dir_count {
-            let ray = self.rook_rays[sq_idx][dir_idx];
-            
-            // Find blockers in this ray
-            let blockers = ray.intersect(occupied);
-            
-            if blockers.is_empty() {
-                // No blockers, entire ray is valid
-                attacks = attacks.union(ray);
-            } else {
-                // Find the first blocker
-                let first_blocker = Self::first_set_bit(blockers);
-                if let Some(blocker_sq) = first_blocker {
-                    // Include squares up to and including the blocker
-                    let mask = Self::ray_mask(sq, blocker_sq, &self.dims);
-                    attacks = attacks.union(ray.intersect(mask));
-                }
-            }
+        let mut line_cache: [Option<B>; 4] = [None; 4];
+        let mut attacks = B::empty_for_dims(&self.dims);
+
+        for dir in directions {
+            let dir_idx = UNIT_DIRS.iter().position(|(unit, _)| unit == dir)?;
+            let line = UNIT_DIRS[dir_idx].1;
+            let line_attacks = *line_cache[line]
+                .get_or_insert_with(|| self.line_attacks(sq, occupied, line));
+            attacks = attacks.union(line_attacks.intersect(self.rays[sq_idx][dir_idx]));
         }
-        
-        attacks
+
+        if let Some(max) = max_distance {
+            attacks = attacks.intersect(self.distance_mask(sq, max));
+        }
+        Some(attacks)
     }
-    
-    pub fn bishop_attacks(&self, sq: Square, occupied: BitBoard) -> BitBoard {
-        let sq_idx = sq.idx() as usize;
-        let mut attacks = BitBoard::empty_for_dims(&self.dims);
-        
-        for dir_idx in 0..self.bishop_dir_count {
-            let ray = self.bishop_rays[sq_idx][dir_idx];
-            
-            let blockers = ray.intersect(occupied);
-            
-            if blockers.is_empty() {
-                attacks = attacks.union(ray);
-            } else {
-                let first_blocker = Self::first_set_bit(blockers);
-                if let Some(blocker_sq) = first_blocker {
-                    let mask = Self::ray_mask(sq, blocker_sq, &self.dims);
-                    attacks = attacks.union(ray.intersect(mask));
-                }
+
+    /// Obstruction difference: the nearest blocker below the slider (or square 0)
+    /// is subtracted from the blockers above it, which sets every bit up to and
+    /// including the nearest blocker above. Square indices increase monotonically
+    /// along every rank, file and diagonal, so this works for any `Dimensions` and
+    /// either bitboard width.
+    #[inline]
+    fn line_attacks(&self, sq: Square, occupied: B, line: usize) -> B {
+        let rays = &self.rays[sq.idx() as usize];
+        let upper = occupied.intersect(rays[2 * line]);
+        let lower = occupied.intersect(rays[2 * line + 1]);
+
+        let nearest_lower = match lower.msb() {
+            Some(blocker) => B::empty_for_dims(&self.dims).set(blocker),
+            None => self.first_square,
+        };
+        upper.xor(upper.wrapping_sub(nearest_lower))
+            .intersect(self.lines[sq.idx() as usize][line])
+    }
+
+    /// Squares within `distance` king steps of `sq`
+    fn distance_mask(&self, sq: Square, distance: u8) -> B {
+        let (file, rank) = sq.file_rank(&self.dims);
+        let files = Self::band(&self.files_upto, file, distance);
+        let ranks = Self::band(&self.ranks_upto, rank, distance);
+        files.intersect(ranks)
+    }
+
+    fn band(upto: &[B], center: u8, distance: u8) -> B {
+        let high = (center as usize + distance as usize).min(upto.len() - 1);
+        let below = match center.checked_sub(distance) {
+            Some(low) if low > 0 => upto[low as usize - 1],
+            _ => return upto[high],
+        };
+        upto[high].difference(below)
+    }
+
+    fn precompute_prefix_masks(dims: Dimensions) -> (Vec<B>, Vec<B>) {
+        let mut files_upto = Vec::with_capacity(dims.width as usize);
+        let mut ranks_upto = Vec::with_capacity(dims.height as usize);
+        let mut files = B::empty_for_dims(&dims);
+        let mut ranks = B::empty_for_dims(&dims);
+
+        for file in 0..dims.width {
+            for rank in 0..dims.height {
+                files = files.set(Square::from_rank_file(rank, file, &dims));
             }
+            files_upto.push(files);
         }
-        
-        attacks
-    }
-    
-    /// Get queen attacks (rook + bishop)
-    pub fn queen_attacks(&self, sq: Square, occupied: BitBoard) -> BitBoard {
-        self.rook_attacks(sq, occupied).union(self.bishop_attacks(sq, occupied))
+        for rank in 0..dims.height {
+            for file in 0..dims.width {
+                ranks = ranks.set(Square::from_rank_file(rank, file, &dims));
+            }
+            ranks_upto.push(ranks);
+        }
+
+        (files_upto, ranks_upto)
     }
-    
-    
-    fn precompute_knight_attacks(dims: Dimensions) -> Vec<BitBoard> {
+
+    fn precompute_knight_attacks(dims: Dimensions) -> Vec<B> {
         let num_squares = dims.num_squares() as usize;
-        let mut attacks = vec![BitBoard::empty_for_dims(&dims); num_squares];
-        
+        let mut attacks = vec![B::empty_for_dims(&dims); num_squares];
+
         let knight_offsets = [
             (1, 2), (2, 1), (-1, 2), (-2, 1),
             (1, -2), (2, -1), (-1, -2), (-2, -1),
         ];
-        
+
         for rank in 0..dims.height {
             for file in 0..dims.width {
                 let sq = Square::from_rank_file(rank, file, &dims);
-                let mut attack_bb = BitBoard::empty_for_dims(&dims);
-                
+                let mut attack_bb = B::empty_for_dims(&dims);
+
                 for &(df, dr) in &knight_offsets {
                     let target_file = file as i8 + df;
                     let target_rank = rank as i8 + dr;
-                    
+
                     if target_file >= 0 && target_file < dims.width as i8 &&
                        target_rank >= 0 && target_rank < dims.height as i8 {
                         let target_sq = Square::from_rank_file(
@@ -130,32 +230,32 @@ impl AttackTable {
                         attack_bb = attack_bb.set(target_sq);
                     }
                 }
-                
+
                 attacks[sq.idx() as usize] = attack_bb;
             }
         }
-        
+
         attacks
     }
-    
-    fn precompute_king_attacks(dims: Dimensions) -> Vec<BitBoard> {
+
+    fn precompute_king_attacks(dims: Dimensions) -> Vec<B> {
         let num_squares = dims.num_squares() as usize;
-        let mut attacks = vec![BitBoard::empty_for_dims(&dims); num_squares];
-        
+        let mut attacks = vec![B::empty_for_dims(&dims); num_squares];
+
         let king_offsets = [
             (0, 1), (1, 1), (1, 0), (1, -1),
             (0, -1), (-1, -1), (-1, 0), (-1, 1),
         ];
-        
+
         for rank in 0..dims.height {
             for file in 0..dims.width {
                 let sq = Square::from_rank_file(rank, file, &dims);
-                let mut attack_bb = BitBoard::empty_for_dims(&dims);
-                
+                let mut attack_bb = B::empty_for_dims(&dims);
+
                 for &(df, dr) in &king_offsets {
                     let target_file = file as i8 + df;
                     let target_rank = rank as i8 + dr;
-                    
+
                     if target_file >= 0 && target_file < dims.width as i8 &&
                        target_rank >= 0 && target_rank < dims.height as i8 {
                         let target_sq = Square::from_rank_file(
@@ -166,92 +266,37 @@ impl AttackTable {
                         attack_bb = attack_bb.set(target_sq);
                     }
                 }
-                
+
                 attacks[sq.idx() as usize] = attack_bb;
             }
         }
-        
+
         attacks
     }
-    
-    fn precompute_rook_rays(dims: Dimensions) -> (Vec<Vec<BitBoard>>, usize) {
-        let num_squares = dims.num_squares() as usize;
-        let directions = [
-            (0, 1),
-            (0, -1),
-            (1, 0),
-            (-1, 0),
-        ];
-        
-        let empty_bb = BitBoard::empty_for_dims(&dims);
-        let mut rays = vec![vec![empty_bb; directions.len()]; num_squares];
-        
-        for rank in 0..dims.height {
-            for file in 0..dims.width {
-                let sq = Square::from_rank_file(rank, file, &dims);
-                let sq_idx = sq.idx() as usize;
-                
-                for (dir_idx, &(df, dr)) in directions.iter().enumerate() {
-                    let mut ray = BitBoard::empty_for_dims(&dims);
-                    let mut current_file = file as i8;
-                    let mut current_rank = rank as i8;
-                    
-                    loop {
-                        current_file += df;
-                        current_rank += dr;
-                        
-                        if current_file < 0 || current_file >= dims.width as i8 ||
-                           current_rank < 0 || current_rank >= dims.height as i8 {
-                            break;
-                        }
-                        
-                        let target_sq = Square::from_rank_file(
-                            current_rank as u8,
-                            current_file as u8,
-                            &dims,
-                        );
-                        ray = ray.set(target_sq);
-                    }
-                    
-                    rays[sq_idx][dir_idx] = ray;
-                }
-            }
-        }
-        
-        (rays, directions.len())
-    }
-    
-    fn precompute_bishop_rays(dims: Dimensions) -> (Vec<Vec<BitBoard>>, usize) {
+
+    fn precompute_rays(dims: Dimensions) -> Vec<[B; 8]> {
         let num_squares = dims.num_squares() as usize;
-        let directions = [
-            (1, 1),    // Northeast
-            (1, -1),   // Southeast
-            (-1, -1),  // Southwest
-            (-1, 1),   // Northwest
-        ];
-        
-        let empty_bb = BitBoard::empty_for_dims(&dims);
-        let mut rays = vec![vec![empty_bb; directions.len()]; num_squares];
-        
+        let mut rays = vec![[B::empty_for_dims(&dims); 8]; num_squares];
+
         for rank in 0..dims.height {
             for file in 0..dims.width {
                 let sq = Square::from_rank_file(rank, file, &dims);
                 let sq_idx = sq.idx() as usize;
-                
-                for (dir_idx, &(df, dr)) in directions.iter().enumerate() {
-                    let mut ray = BitBoard::empty_for_dims(&dims);
+
+                for (dir_idx, &(dir, _)) in UNIT_DIRS.iter().enumerate() {
+                    let mut ray = B::empty_for_dims(&dims);
                     let mut current_file = file as i8;
                     let mut current_rank = rank as i8;
-                    
+
                     loop {
-                        current_file += df;
-                        current_rank += dr;
-                        
+                        current_file += dir.file_delta;
+                        current_rank += dir.rank_delta;
+
                         if current_file < 0 || current_file >= dims.width as i8 ||
                            current_rank < 0 || current_rank >= dims.height as i8 {
                             break;
                         }
-                        
+
                         let target_sq = Square::from_rank_file(
                             current_rank as u8,
                             current_file as u8,
@@ -259,57 +304,64 @@ impl AttackTable {
                         );
                         ray = ray.set(target_sq);
                     }
-                    
+
                     rays[sq_idx][dir_idx] = ray;
                 }
             }
         }
-        
-        (rays, directions.len())
+
+        rays
     }
-    
-    #[inline]
-    fn first_set_bit(bb: BitBoard) -> Option<Square> {
-        if bb.is_empty() {
-            None
-        } else {
-            let mut temp = bb;
-            temp.pop_lsb()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64};
+    use crate::movegen::patterns::{MovePattern, SlidingPattern};
+    use crate::piece::Color;
+
+    /// Compare every slider against the square-by-square walk of `SlidingPattern`
+    fn check_against_walk<B: BB>(dims: Dimensions) {
+        let table: AttackTable<B> = AttackTable::new(dims);
+        let rook = SlidingPattern::new(Direction::ROOK_DIRS.to_vec());
+        let bishop = SlidingPattern::new(Direction::BISHOP_DIRS.to_vec());
+        let short = SlidingPattern::with_max_distance(vec![Direction::NORTH, Direction::SOUTHWEST], 2);
+        let none = B::empty_for_dims(&dims);
+
+        // a fixed scatter of blockers
+        let mut occupied = B::empty_for_dims(&dims);
+        for idx in (0..dims.num_squares()).filter(|idx| (idx * 7 + idx / 3) % 5 == 0) {
+            occupied = occupied.set(Square(idx));
         }
-    }
-    
-    /// Create a mask for squares between (and including) two squares on a ray
-    fn ray_mask(from: Square, to: Square, dims: &Dimensions) -> BitBoard {
-        let (from_file, from_rank) = from.file_rank(dims);
-        let (to_file, to_rank) = to.file_rank(dims);
-        
-        let file_delta = to_file as i8 - from_file as i8;
-        let rank_delta = to_rank as i8 - from_rank as i8;
-        
-        let file_step = if file_delta != 0 { file_delta.signum() } else { 0 };
-        let rank_step = if rank_delta != 0 { rank_delta.signum() } else { 0 };
-        
-        let mut mask = BitBoard::empty_for_dims(&dims);
-        let mut current_file = from_file as i8;
-        let mut current_rank = from_rank as i8;
-        
-        loop {
-            let sq = Square::from_rank_file(
-                current_rank as u8,
-                current_file as u8,
-                dims,
+
+        for idx in 0..dims.num_squares() {
+            let sq = Square(idx);
+            assert_eq!(table.rook_attacks(sq, occupied), rook.attacks_from(sq, Color::White, &dims, occupied, none));
+            assert_eq!(table.bishop_attacks(sq, occupied), bishop.attacks_from(sq, Color::White, &dims, occupied, none));
+            assert_eq!(
+                table.slider_attacks(sq, occupied, &[Direction::NORTH, Direction::SOUTHWEST], Some(2)),
+                Some(short.attacks_from(sq, Color::White, &dims, occupied, none)),
             );
-            mask = mask.set(sq);
-            
-            if current_file == to_file as i8 && current_rank == to_rank as i8 {
-                break;
-            }
-            
-            current_file += file_step;
-            current_rank += rank_step;
         }
-        
-        mask
     }
-}
 
+    #[test]
+    fn slider_attacks_match_walk_on_all_sizes() {
+        check_against_walk::<BitBoard64>(Dimensions::standard());
+        check_against_walk::<BitBoard64>(Dimensions::new(7, 9));
+        check_against_walk::<BitBoard256>(Dimensions::new(10, 10));
+        check_against_walk::<BitBoard256>(Dimensions::new(16, 16));
+        check_against_walk::<BitBoard256>(Dimensions::new(13, 6));
+        check_against_walk::<BitBoard>(Dimensions::new(12, 12));
+    }
+
+    #[test]
+    fn slider_attacks_reject_non_unit_directions() {
+        let dims = Dimensions::standard();
+        let table: AttackTable<BitBoard64> = AttackTable::new(dims);
+        let knight_step = Direction { file_delta: 1, rank_delta: 2 };
+
+        assert!(table.slider_attacks(Square(0), BitBoard64::empty(), &[knight_step], None).is_none());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    board::{Dimensions, Square, BB},
+    piece::{Color, PieceKind},
+};
+
+/// Where pieces from the pocket may be dropped. Without restrictions any kind may
+/// go to any empty square.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DropRules {
+    /// (kind, closed ranks at the dropping side's end, closed ranks at the far end)
+    restrictions: Vec<(PieceKind, u8, u8)>,
+}
+
+impl DropRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Crazyhouse: pawns may not be dropped on the first or last rank
+    pub fn crazyhouse() -> Self {
+        Self::new().forbid_ranks(PieceKind::Pawn, 1, 1)
+    }
+
+    /// Keep `kind` off the `own` ranks nearest the dropping side and the `far` ranks
+    /// nearest the opponent, e.g. `(Pawn, 1, 1)` for Crazyhouse pawns or
+    /// `(knight, 0, 2)` for Shogi knights
+    pub fn forbid_ranks(mut self, kind: PieceKind, own: u8, far: u8) -> Self {
+        self.restrictions.retain(|&(other, _, _)| other != kind);
+        self.restrictions.push((kind, own, far));
+        self
+    }
+
+    /// Closed ranks for `kind` as (own end, far end)
+    pub fn forbidden_ranks(&self, kind: PieceKind) -> (u8, u8) {
+        self.restrictions
+            .iter()
+            .find(|&&(other, _, _)| other == kind)
+            .map_or((0, 0), |&(_, own, far)| (own, far))
+    }
+
+    /// Restricted kinds with the squares `[white, black]` may drop them on
+    pub(crate) fn masks<B: BB>(&self, dims: &Dimensions) -> Vec<(PieceKind, [B; 2])> {
+        self.restrictions
+            .iter()
+            .map(|&(kind, own, far)| {
+                let mask = |color: Color| {
+                    let mut mask = B::empty_for_dims(dims);
+                    for idx in 0..dims.num_squares() {
+                        let sq = Square(idx);
+                        let (_, rank) = sq.file_rank(dims);
+                        let relative = match color {
+                            Color::White => rank,
+                            Color::Black => dims.height - 1 - rank,
+                        };
+                        if relative >= own && (relative as u16) + (far as u16) < dims.height as u16 {
+                            mask = mask.set(sq);
+                        }
+                    }
+                    mask
+                };
+                (kind, [mask(Color::White), mask(Color::Black)])
+            })
+            .collect()
+    }
+}
@@ -1,113 +1,488 @@
 use crate::{
     board::{Dimensions, Square, BitBoard, BB},
-    piece::{PieceKind, Color, Piece},
+    piece::{PieceKind, Color, Piece, PieceRegistry},
     position::Position,
     moves::{Move, MoveType},
     movegen::patterns::MovePattern,
     movegen::standard::StandardPatterns,
     movegen::attack_table::AttackTable,
+    movegen::drops::DropRules,
+    movegen::move_list::MoveList,
 };
-use std::collections::HashMap;
 
+/// Pieces a pawn may promote to, in generation order
+const PROMOTION_KINDS: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
 
-pub struct MoveGenerator {
+/// Which part of the pseudo-legal moves a generation call produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenType {
+    /// Every pseudo-legal move
+    All,
+    /// Captures, en passant and every promotion, capturing or not
+    Captures,
+    /// Everything `Captures` leaves out: quiet moves, castling and drops
+    Quiets,
+    /// In check, the king's moves and the moves that capture the checker or block
+    /// its line; with several checkers only the king's moves. A custom piece giving
+    /// check may be stopped in other ways, such as moving a hopper's screen, so then
+    /// every move but castling is kept. Without check the same as `All`.
+    Evasions,
+}
+
+/// Squares a generation stage lets moves go to
+struct Targets<B> {
+    /// Enemy pieces that may be captured
+    captures: B,
+    /// Empty squares for moves that neither capture nor promote
+    quiets: B,
+    /// `captures` and `quiets` together, for pieces other than the king and pawns
+    moves: B,
+    /// Empty squares a pawn may promote on without capturing
+    promotions: B,
+    /// Squares the king may go to
+    king: B,
+    /// Empty squares an en passant capture may land on, besides taking a piece in
+    /// `captures`; used by evasions to block with it
+    ep_blocks: B,
+    /// Squares pocket pieces may be dropped on
+    drops: B,
+    castling: bool,
+}
+
+pub struct MoveGenerator<B = BitBoard> {
     dims: Dimensions,
-    attack_table: AttackTable,
-    custom_patterns: HashMap<PieceKind, Box<dyn MovePattern>>,
+    attack_table: AttackTable<B>,
+    registry: PieceRegistry<B>,
+    drop_rules: DropRules,
+    /// Every square of the board
+    board: B,
+    /// Drop squares `[white, black]` of the kinds `drop_rules` restricts
+    drop_masks: Vec<(PieceKind, [B; 2])>,
 }
 
-impl MoveGenerator {
+impl<B: BB> MoveGenerator<B> {
+    /// Generator for the standard chess pieces
     pub fn new(dims: Dimensions) -> Self {
+        Self::with_registry(dims, PieceRegistry::standard())
+    }
+    
+    /// Generator for the pieces in `registry`. Custom kinds move by their registered
+    /// pattern; a custom kind without one has no moves and attacks nothing.
+    pub fn with_registry(dims: Dimensions, registry: PieceRegistry<B>) -> Self {
         let attack_table = AttackTable::new(dims);
+        let board = (0..dims.num_squares()).fold(B::empty_for_dims(&dims), |bb, idx| bb.set(Square(idx)));
         Self {
             dims,
             attack_table,
-            custom_patterns: HashMap::new(),
+            registry,
+            drop_rules: DropRules::new(),
+            board,
+            drop_masks: Vec::new(),
         }
     }
     
-    pub fn register_custom_pattern(&mut self, kind: PieceKind, pattern: Box<dyn MovePattern>) {
-        self.custom_patterns.insert(kind, pattern);
+    pub fn drop_rules(&self) -> &DropRules {
+        &self.drop_rules
+    }
+    
+    pub fn set_drop_rules(&mut self, rules: DropRules) {
+        self.drop_masks = rules.masks(&self.dims);
+        self.drop_rules = rules;
+    }
+    
+    pub fn registry(&self) -> &PieceRegistry<B> {
+        &self.registry
+    }
+    
+    pub fn register_custom_pattern(&mut self, kind: PieceKind, pattern: Box<dyn MovePattern<B>>) {
+        self.registry.set_pattern(kind, pattern);
+    }
+    
+    pub fn generate_pseudo_legal(&self, pos: &Position<B>) -> Vec<Move> {
+        self.generate(pos, GenType::All)
+    }
+    
+    /// Generate only the moves that do not leave the side to move in check
+    pub fn generate_legal(&self, pos: &Position<B>) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_legal_into(pos, &mut moves);
+        moves.to_vec()
     }
     
-    pub fn generate_pseudo_legal(&self, pos: &Position) -> Vec<Move> {
-        let mut moves = Vec::new();
+    /// Generate the pseudo-legal moves of one stage, see `GenType`
+    pub fn generate(&self, pos: &Position<B>, gen_type: GenType) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_into(pos, gen_type, &mut moves);
+        moves.to_vec()
+    }
+    
+    /// Append the pseudo-legal moves to `moves` without allocating
+    pub fn generate_pseudo_legal_into(&self, pos: &Position<B>, moves: &mut MoveList) {
+        self.generate_into(pos, GenType::All, moves);
+    }
+    
+    /// Append the pseudo-legal moves of one stage to `moves`. `Captures` and `Quiets`
+    /// split `All` without overlap; `Evasions` is a superset of the legal moves.
+    pub fn generate_into(&self, pos: &Position<B>, gen_type: GenType, moves: &mut MoveList) {
         let color = pos.side_to_move;
         let friendly = pos.color_bb(color);
         let enemy = pos.color_bb(color.opposite());
-        let occupied = pos.all;
+        let occupied = pos.blockers();
+        let targets = self.targets(pos, gen_type);
         
-        for (&kind, &kind_bb) in pos.pieces.iter() {
-            let pieces_bb = kind_bb.intersect(friendly);
-            let mut pieces_bb = pieces_bb;
+        for &kind in pos.kinds() {
+            let mut pieces_bb = pos.kind_bb(kind).intersect(friendly);
+            let allowed = if kind == PieceKind::King { targets.king } else { targets.moves };
             
             while !pieces_bb.is_empty() {
                 let Some(sq) = pieces_bb.pop_lsb() else { break };
                 
                 if kind == PieceKind::Pawn {
-                    self.generate_pawn_moves(pos, sq, color, &mut moves);
+                    self.generate_pawn_moves(pos, sq, color, &targets, moves);
                     continue;
                 }
                 
-                let pattern: &dyn MovePattern = if let Some(custom) = self.custom_patterns.get(&kind) {
-                    custom.as_ref()
-                } else { 
-                    self.generate_standard_moves(pos, sq, kind, color, &mut moves);
-                    continue;
+                let pattern: &dyn MovePattern<B> = match self.registry.pattern(kind) {
+                    Some(pattern) => pattern,
+                    // custom kinds without a pattern cannot move
+                    None if matches!(kind, PieceKind::Custom(_)) => break,
+                    None => {
+                        self.generate_standard_moves(pos, sq, kind, allowed, moves);
+                        continue;
+                    }
                 };
                 
-                let mut attacks = pattern.attacks_from(sq, &self.dims, occupied, friendly);
+                let unreachable = friendly.union(pos.walls);
+                let attacks = pattern.attacks_with_table(&self.attack_table, sq, color, occupied, unreachable);
+                let mut attacks = match pattern.quiet_moves_from(sq, color, &self.dims, occupied, unreachable) {
+                    Some(quiets) => attacks.intersect(enemy).union(quiets.difference(occupied)),
+                    None => attacks,
+                }
+                .intersect(allowed);
                 
                 while !attacks.is_empty() {
                     let Some(target) = attacks.pop_lsb() else { break };
                     
-                    let move_type = if enemy.contains(target) {
-                        MoveType::Capture
-                    } else {
-                        MoveType::Quiet
-                    };
-                    
-                    moves.push(Move::new(sq, target, move_type, 0));
+                    moves.push(Self::step(pos, sq, target));
                 }
             }
         }
         
-        self.generate_castling_moves(pos, color, &mut moves);
+        if targets.castling {
+            self.generate_castling_moves(pos, color, moves);
+        }
+        self.generate_drops(pos, color, targets.drops, moves);
+    }
+    
+    /// Append the legal moves to `moves`; moves already in the list are kept as they are
+    pub fn generate_legal_into(&self, pos: &Position<B>, moves: &mut MoveList) {
+        let start = moves.len();
+        self.generate_pseudo_legal_into(pos, moves);
+        // compact the new moves in place, keeping generation order
+        let mut kept = start;
+        for idx in start..moves.len() {
+            let mv = moves[idx];
+            if self.is_legal(pos, mv) {
+                moves[kept] = mv;
+                kept += 1;
+            }
+        }
+        moves.truncate(kept);
+    }
+    
+    /// Check whether a pseudo-legal move leaves every king of the mover unattacked.
+    ///
+    /// The move is applied to the occupancy only, so pins, discovered checks through
+    /// an en passant capture and king walks into attacked squares all fall out of the
+    /// same attack test. Positions without a king of the side to move accept every move.
+    pub fn is_legal(&self, pos: &Position<B>, mv: Move) -> bool {
+        let us = pos.side_to_move;
+        let them = us.opposite();
+        let src = mv.src();
+        let dst = mv.dst();
+        
+        if mv.kind() == MoveType::Castling {
+            return self.is_castling_legal(pos, mv);
+        }
+        
+        let mut kings = pos.piece_bb(us, PieceKind::King);
+        if kings.contains(src) {
+            kings = kings.clear(src).set(dst);
+        }
+        
+        let mut removed = B::empty_for_dims(&self.dims).set(dst);
+        if mv.kind() == MoveType::EnPassant {
+            removed = removed.set(pos.ep_capture_square(src, dst));
+        }
+        
+        let occupied = pos.blockers().difference(removed).clear(src).set(dst);
+        let attackers = pos.color_bb(them).difference(removed);
         
-        moves
+        while let Some(king_sq) = kings.pop_lsb() {
+            if self.is_attacked_with(pos, king_sq, them, occupied, attackers) {
+                return false;
+            }
+        }
+        true
+    }
+    
+    /// Check whether any piece of `by` attacks `sq` in the current position
+    pub fn is_square_attacked(&self, pos: &Position<B>, sq: Square, by: Color) -> bool {
+        self.is_attacked_with(pos, sq, by, pos.blockers(), pos.color_bb(by))
+    }
+    
+    /// The squares of the `by` pieces that attack `sq`, whoever stands on it. Pieces
+    /// of `by` on `sq` count as defenders for exchange evaluation and threat maps.
+    pub fn attackers_to(&self, pos: &Position<B>, sq: Square, by: Color) -> B {
+        self.attackers_to_with(pos, sq, by, pos.blockers())
+    }
+    
+    /// Same as `attackers_to`, but against `occupied` instead of the board's blockers,
+    /// so pieces removed from it are gone and sliders behind them attack through.
+    /// Pieces of `by` are only counted where `occupied` still holds them.
+    pub fn attackers_to_with(&self, pos: &Position<B>, sq: Square, by: Color, occupied: B) -> B {
+        self.attackers_with(pos, sq, by, occupied, pos.color_bb(by).intersect(occupied))
+    }
+    
+    /// The enemy pieces attacking a king of the side to move
+    pub fn checkers(&self, pos: &Position<B>) -> B {
+        let us = pos.side_to_move;
+        let mut kings = pos.piece_bb(us, PieceKind::King);
+        let mut found = B::empty_for_dims(&self.dims);
+        while let Some(king_sq) = kings.pop_lsb() {
+            found = found.union(self.attackers_to(pos, king_sq, us.opposite()));
+        }
+        found
+    }
+    
+    /// Check whether a king of the side to move is attacked
+    pub fn in_check(&self, pos: &Position<B>) -> bool {
+        let us = pos.side_to_move;
+        let mut kings = pos.piece_bb(us, PieceKind::King);
+        while let Some(king_sq) = kings.pop_lsb() {
+            if self.is_square_attacked(pos, king_sq, us.opposite()) {
+                return true;
+            }
+        }
+        false
+    }
+    
+    /// Attack test against a hypothetical occupancy, where `attackers` holds the
+    /// squares of the `by` pieces that are still on the board
+    fn is_attacked_with(
+        &self,
+        pos: &Position<B>,
+        sq: Square,
+        by: Color,
+        occupied: B,
+        attackers: B,
+    ) -> bool {
+        !self.attackers_walk(pos, sq, by, occupied, attackers, true).is_empty()
+    }
+    
+    /// The squares of `attackers` holding `by` pieces that attack `sq` under `occupied`
+    fn attackers_with(
+        &self,
+        pos: &Position<B>,
+        sq: Square,
+        by: Color,
+        occupied: B,
+        attackers: B,
+    ) -> B {
+        self.attackers_walk(pos, sq, by, occupied, attackers, false)
+    }
+    
+    /// Shared walk of `is_attacked_with` and `attackers_with`; with `first_only` it
+    /// returns as soon as some attacker turns up
+    fn attackers_walk(
+        &self,
+        pos: &Position<B>,
+        sq: Square,
+        by: Color,
+        occupied: B,
+        attackers: B,
+        first_only: bool,
+    ) -> B {
+        let mut found = B::empty_for_dims(&self.dims);
+        for &kind in pos.kinds() {
+            let candidates = pos.kind_bb(kind).intersect(attackers);
+            if candidates.is_empty() {
+                continue;
+            }
+            
+            if kind == PieceKind::Pawn {
+                let empty = B::empty_for_dims(&self.dims);
+                let reach = StandardPatterns::pawn_attacks(sq, by.opposite(), &self.dims, empty);
+                found = found.union(reach.intersect(candidates));
+            } else if let Some(custom) = self.registry.pattern(kind) {
+                // Custom patterns need not be symmetric, so ask every piece directly;
+                // `sq` is left out of the friendly set so defenders count too
+                let friendly = attackers.clear(sq);
+                let mut from_bb = candidates;
+                while let Some(from) = from_bb.pop_lsb() {
+                    if custom.attacks_with_table(&self.attack_table, from, by, occupied, friendly).contains(sq) {
+                        found = found.set(from);
+                        if first_only {
+                            return found;
+                        }
+                    }
+                }
+            } else {
+                let reach = match kind {
+                    PieceKind::Knight => self.attack_table.knight_attacks(sq),
+                    PieceKind::Bishop => self.attack_table.bishop_attacks(sq, occupied),
+                    PieceKind::Rook => self.attack_table.rook_attacks(sq, occupied),
+                    PieceKind::Queen => self.attack_table.queen_attacks(sq, occupied),
+                    PieceKind::King => self.attack_table.king_attacks(sq),
+                    _ => continue,
+                };
+                found = found.union(reach.intersect(candidates));
+            }
+            if first_only && !found.is_empty() {
+                return found;
+            }
+        }
+        found
+    }
+    
+    /// Target squares of `gen_type` for the side to move
+    fn targets(&self, pos: &Position<B>, gen_type: GenType) -> Targets<B> {
+        let us = pos.side_to_move;
+        let none = B::empty_for_dims(&self.dims);
+        let enemy = pos.color_bb(us.opposite()).difference(pos.walls);
+        let empty = self.board.difference(pos.blockers());
+        let all = Targets {
+            captures: enemy,
+            quiets: empty,
+            moves: enemy.union(empty),
+            promotions: empty,
+            king: enemy.union(empty),
+            ep_blocks: none,
+            drops: empty,
+            castling: true,
+        };
+        
+        match gen_type {
+            GenType::All => all,
+            GenType::Captures => Targets {
+                quiets: none,
+                moves: enemy,
+                king: enemy,
+                drops: none,
+                castling: false,
+                ..all
+            },
+            GenType::Quiets => Targets {
+                captures: none,
+                moves: empty,
+                promotions: none,
+                king: empty,
+                ..all
+            },
+            GenType::Evasions => self.evasion_targets(pos, all),
+        }
+    }
+    
+    /// Narrow `all` to the moves that may get a lone king of the side to move out of check
+    fn evasion_targets(&self, pos: &Position<B>, all: Targets<B>) -> Targets<B> {
+        let us = pos.side_to_move;
+        let them = us.opposite();
+        let none = B::empty_for_dims(&self.dims);
+        
+        let mut kings = pos.piece_bb(us, PieceKind::King);
+        let Some(king_sq) = kings.pop_lsb() else { return all };
+        if !kings.is_empty() {
+            return all;
+        }
+        
+        let occupied = pos.blockers();
+        let mut checkers = self.attackers_to(pos, king_sq, them);
+        if checkers.is_empty() {
+            return all;
+        }
+        let evading_king = Targets { castling: false, ..all };
+        // a custom checker may be stopped off its line, e.g. by moving a hopper's screen
+        let mut remaining = checkers;
+        while let Some(sq) = remaining.pop_lsb() {
+            if matches!(pos.piece_at(sq), Some(Piece { kind: PieceKind::Custom(_), .. })) {
+                return evading_king;
+            }
+        }
+        if checkers.count() > 1 {
+            return Targets {
+                captures: none,
+                quiets: none,
+                moves: none,
+                promotions: none,
+                ep_blocks: none,
+                drops: none,
+                ..evading_king
+            };
+        }
+        
+        let checker_bb = checkers;
+        let Some(checker) = checkers.pop_lsb() else { return evading_king };
+        let (king_file, king_rank) = king_sq.file_rank(&self.dims);
+        let (file, rank) = checker.file_rank(&self.dims);
+        let blocks = match pos.piece_at(checker).map(|piece| piece.kind) {
+            Some(PieceKind::Rook | PieceKind::Queen) if file == king_file || rank == king_rank => self
+                .attack_table
+                .rook_attacks(king_sq, occupied)
+                .intersect(self.attack_table.rook_attacks(checker, occupied)),
+            Some(PieceKind::Bishop | PieceKind::Queen) if file.abs_diff(king_file) == rank.abs_diff(king_rank) => self
+                .attack_table
+                .bishop_attacks(king_sq, occupied)
+                .intersect(self.attack_table.bishop_attacks(checker, occupied)),
+            _ => none,
+        };
+        
+        Targets {
+            captures: checker_bb,
+            quiets: blocks,
+            moves: checker_bb.union(blocks),
+            promotions: blocks,
+            ep_blocks: blocks,
+            drops: blocks.intersect(all.drops),
+            ..evading_king
+        }
+    }
+    
+    /// The king may not castle out of, through or into check
+    fn is_castling_legal(&self, pos: &Position<B>, mv: Move) -> bool {
+        let them = pos.side_to_move.opposite();
+        let (src_file, rank) = mv.src().file_rank(&self.dims);
+        let (dst_file, _) = mv.dst().file_rank(&self.dims);
+        
+        (src_file.min(dst_file)..=src_file.max(dst_file)).all(|file| {
+            let sq = Square::from_rank_file(rank, file, &self.dims);
+            !self.is_square_attacked(pos, sq, them)
+        })
     }
     
     fn generate_castling_moves(
         &self,
-        pos: &Position,
+        pos: &Position<B>,
         color: Color,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
         if self.dims.width < 5 || self.dims.height < 5 {
             return;
         }
         
-        let (king_rank, kingside_rook_file, queenside_rook_file) = match color {
-            Color::White => (0, self.dims.width - 1, 0),
-            Color::Black => (self.dims.height - 1, self.dims.width - 1, 0),
-        };
-        
-        let king_file = match color {
-            Color::White => {
-                if self.dims.width >= 8 { 4 } else { self.dims.width / 2 }
-            }
-            Color::Black => {
-                if self.dims.width >= 8 { 4 } else { self.dims.width / 2 }
-            }
-        };
-        
-        let king_sq = Square::from_rank_file(king_rank, king_file, &self.dims);
+        let king_sq = pos.castling_king_square(color);
+        let (king_file, king_rank) = king_sq.file_rank(&self.dims);
+        let (kingside_rook_file, queenside_rook_file) = (self.dims.width - 1, 0);
         
         if pos.piece_at(king_sq) != Some(Piece { color, kind: PieceKind::King }) {
             return;
         }
         
-        let occupied = pos.all;
+        let occupied = pos.blockers();
         
 
         if match color {
@@ -131,14 +506,15 @@ impl MoveGenerator {
                 
                 if can_castle {
                     let castling_king_file = if kingside_rook_file > king_file {
-                        king_file + 2
+                        king_file.checked_add(2)
                     } else {
-                        king_file - 2
+                        king_file.checked_sub(2)
                     };
                     
-                    if castling_king_file < self.dims.width {
+                    let on_board = |&file: &u8| file < self.dims.width && file != kingside_rook_file;
+                    if let Some(castling_king_file) = castling_king_file.filter(on_board) {
                         let castling_king_sq = Square::from_rank_file(king_rank, castling_king_file, &self.dims);
-                        moves.push(Move::new(king_sq, castling_king_sq, MoveType::Castling, 0));
+                        moves.push(Move::new(king_sq, castling_king_sq, MoveType::Castling));
                     }
                 }
             }
@@ -165,31 +541,49 @@ impl MoveGenerator {
                 
                 if can_castle {
                     let castling_king_file = if queenside_rook_file > king_file {
-                        king_file + 2
+                        king_file.checked_add(2)
                     } else {
-                        king_file - 2
+                        king_file.checked_sub(2)
                     };
                     
-                    if castling_king_file < self.dims.width {
+                    let on_board = |&file: &u8| file < self.dims.width && file != queenside_rook_file;
+                    if let Some(castling_king_file) = castling_king_file.filter(on_board) {
                         let castling_king_sq = Square::from_rank_file(king_rank, castling_king_file, &self.dims);
-                        moves.push(Move::new(king_sq, castling_king_sq, MoveType::Castling, 0));
+                        moves.push(Move::new(king_sq, castling_king_sq, MoveType::Castling));
                     }
                 }
             }
         }
     }
     
+    /// Every pocket piece of `color` onto every square of `empty` its drop rules allow
+    fn generate_drops(&self, pos: &Position<B>, color: Color, empty: B, moves: &mut MoveList) {
+        let pocket = pos.pocket(color);
+        if pocket.is_empty() || empty.is_empty() {
+            return;
+        }
+        
+        for (kind, _) in pocket.iter() {
+            let allowed = match self.drop_masks.iter().find(|(restricted, _)| *restricted == kind) {
+                Some((_, masks)) => empty.intersect(masks[color as usize]),
+                None => empty,
+            };
+            let mut targets = allowed;
+            while let Some(sq) = targets.pop_lsb() {
+                moves.push(Move::new_drop(kind, sq));
+            }
+        }
+    }
+    
     fn generate_standard_moves(
         &self,
-        pos: &Position,
+        pos: &Position<B>,
         sq: Square,
         kind: PieceKind,
-        color: Color,
-        moves: &mut Vec<Move>,
+        allowed: B,
+        moves: &mut MoveList,
     ) {
-        let friendly = pos.color_bb(color);
-        let enemy = pos.color_bb(color.opposite());
-        let occupied = pos.all;
+        let occupied = pos.blockers();
         
         let mut attacks = match kind {
             PieceKind::Knight => {
@@ -207,36 +601,39 @@ impl MoveGenerator {
             PieceKind::King => {
                 self.attack_table.king_attacks(sq)
             }
-            _ => BitBoard::empty_for_dims(&self.dims),
+            _ => B::empty_for_dims(&self.dims),
         };
         
-        attacks = attacks.difference(friendly);
+        attacks = attacks.intersect(allowed);
         while !attacks.is_empty() {
             let Some(target) = attacks.pop_lsb() else { break };
             
-            let move_type = if enemy.contains(target) {
-                MoveType::Capture
-            } else {
-                MoveType::Quiet
-            };
-            
-            moves.push(Move::new(sq, target, move_type, 0));
+            moves.push(Self::step(pos, sq, target));
+        }
+    }
+    
+    /// Quiet move or capture of whatever stands on `target`, which is not a friendly piece
+    #[inline]
+    fn step(pos: &Position<B>, sq: Square, target: Square) -> Move {
+        match pos.piece_at(target) {
+            Some(victim) => Move::new(sq, target, MoveType::Capture).with_captured(victim.kind),
+            None => Move::new(sq, target, MoveType::Quiet),
         }
     }
     
     fn generate_pawn_moves(
         &self,
-        pos: &Position,
+        pos: &Position<B>,
         sq: Square,
         color: Color,
-        moves: &mut Vec<Move>,
+        targets: &Targets<B>,
+        moves: &mut MoveList,
     ) {
         let friendly = pos.color_bb(color);
-        let enemy = pos.color_bb(color.opposite());
-        let occupied = pos.all;
+        let occupied = pos.blockers();
         
         let mut attacks = StandardPatterns::pawn_attacks(sq, color, &self.dims, friendly);
-        attacks = attacks.intersect(enemy);
+        attacks = attacks.intersect(targets.captures);
         
         while !attacks.is_empty() {
             let Some(target) = attacks.pop_lsb() else { break };
@@ -247,10 +644,13 @@ impl MoveGenerator {
                 Color::Black => 0,
             };
             
+            let victim = pos.piece_at(target).expect("pawn capture without a victim").kind;
             if rank == promotion_rank {
-                moves.push(Move::new(sq, target, MoveType::Promotion, 0));
+                for promo in PROMOTION_KINDS {
+                    moves.push(Move::new_promotion(sq, target, promo).with_captured(victim));
+                }
             } else {
-                moves.push(Move::new(sq, target, MoveType::Capture, 0));
+                moves.push(Move::new(sq, target, MoveType::Capture).with_captured(victim));
             }
         }
         
@@ -266,16 +666,21 @@ impl MoveGenerator {
             };
             
             if rank == promotion_rank {
-                moves.push(Move::new(sq, target, MoveType::Promotion, 0));
-            } else {
-                moves.push(Move::new(sq, target, MoveType::Quiet, 0));
+                if targets.promotions.contains(target) {
+                    for promo in PROMOTION_KINDS {
+                        moves.push(Move::new_promotion(sq, target, promo));
+                    }
+                }
+            } else if targets.quiets.contains(target) {
+                moves.push(Move::new(sq, target, MoveType::Quiet));
             }
         }
         
         if let Some(ep_sq) = pos.ep_square {
             let attacks = StandardPatterns::pawn_attacks(sq, color, &self.dims, friendly);
-            if attacks.contains(ep_sq) {
-                moves.push(Move::new(sq, ep_sq, MoveType::EnPassant, 0));
+            let victim = pos.ep_capture_square(sq, ep_sq);
+            if attacks.contains(ep_sq) && (targets.captures.contains(victim) || targets.ep_blocks.contains(ep_sq)) {
+                moves.push(Move::new(sq, ep_sq, MoveType::EnPassant).with_captured(PieceKind::Pawn));
             }
         }
     }
@@ -284,14 +689,14 @@ impl MoveGenerator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::board::Dimensions;
+    use crate::board::{BitBoard256, BitBoard64, Dimensions};
     use crate::piece::{Color, PieceKind, Piece};
     use crate::position::Fen;
 
     #[test]
     fn move_generation_king() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
                 let e1 = Square::from_rank_file(0, 4, &dims);
         pos.set_piece(e1, Piece {
             color: Color::White,
@@ -301,14 +706,14 @@ mod tests {
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
         
-        assert!(moves.len() > 0);
+        assert!(!moves.is_empty());
         assert!(moves.len() <= 8);
     }
     
     #[test]
     fn move_generation_knight() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let b1 = Square::from_rank_file(0, 1, &dims);
         pos.set_piece(b1, Piece {
@@ -325,7 +730,7 @@ mod tests {
     #[test]
     fn move_generation_rook() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let a1 = Square::from_rank_file(0, 0, &dims);
         pos.set_piece(a1, Piece {
@@ -342,7 +747,7 @@ mod tests {
     #[test]
     fn move_generation_pawn() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let e2 = Square::from_rank_file(1, 4, &dims);
         pos.set_piece(e2, Piece {
@@ -359,7 +764,7 @@ mod tests {
     #[test]
     fn move_generation_capture() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let a1 = Square::from_rank_file(0, 0, &dims);
         let a7 = Square::from_rank_file(6, 0, &dims);
@@ -379,14 +784,14 @@ mod tests {
         let capture_moves: Vec<_> = moves.iter()
             .filter(|m| m.kind() == MoveType::Capture)
             .collect();
-        assert!(capture_moves.len() > 0);
+        assert!(!capture_moves.is_empty());
     }
     
     #[test]
     fn move_generation_castling() {
         let dims = Dimensions::standard();
         let fen_str = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
@@ -401,7 +806,7 @@ mod tests {
     fn move_generation_en_passant() {
         let dims = Dimensions::standard();
         let fen_str = "rnbqkbnr/pppppppp/8/8/4Pp2/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
@@ -409,14 +814,14 @@ mod tests {
         let ep_moves: Vec<_> = moves.iter()
             .filter(|m| m.kind() == MoveType::EnPassant)
             .collect();
-        assert!(ep_moves.len() > 0);
+        assert!(!ep_moves.is_empty());
     }
     
     #[test]
     fn move_generation_complex_position() {
         let dims = Dimensions::standard();
         let fen_str = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
@@ -427,7 +832,7 @@ mod tests {
     #[test]
     fn move_generation_non_standard_board() {
         let dims = Dimensions::new(8, 8);
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let king_sq = Square::from_rank_file(0, 4, &dims);
         pos.set_piece(king_sq, Piece {
@@ -438,13 +843,13 @@ mod tests {
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
         
-        assert!(moves.len() > 0);
+        assert!(!moves.is_empty());
     }
     
     #[test]
     fn move_generation_small_board() {
         let dims = Dimensions::new(5, 5);
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let king_sq = Square::from_rank_file(2, 2, &dims);
         pos.set_piece(king_sq, Piece {
@@ -462,7 +867,7 @@ mod tests {
     fn move_generation_promotion() {
         let dims = Dimensions::standard();
         let fen_str = "8/4P3/8/8/8/8/8/8 w - - 0 1";
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         let gen = MoveGenerator::new(dims);
         let moves = gen.generate_pseudo_legal(&pos);
@@ -470,7 +875,393 @@ mod tests {
         let promo_moves: Vec<_> = moves.iter()
             .filter(|m| m.kind() == MoveType::Promotion)
             .collect();
-        assert!(promo_moves.len() > 0);
+        assert!(!promo_moves.is_empty());
+    }
+    
+    #[test]
+    fn legal_moves_respect_pins() {
+        let dims = Dimensions::standard();
+        // knight on e2 is pinned by the rook on e8
+        let pos: Position<BitBoard64> = Fen::parse("4r2k/8/8/8/8/8/4N3/4K3 w - - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        
+        let e2 = Square::from_rank_file(1, 4, &dims);
+        let moves = gen.generate_legal(&pos);
+        assert!(moves.iter().all(|m| m.src() != e2));
+    }
+    
+    #[test]
+    fn legal_moves_double_check_only_king() {
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("4r2k/8/8/8/1b6/5N2/8/R3K3 w Q - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        
+        assert!(gen.in_check(&pos));
+        let e1 = Square::from_rank_file(0, 4, &dims);
+        let moves = gen.generate_legal(&pos);
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.src() == e1 && m.kind() != MoveType::Castling));
+    }
+    
+    #[test]
+    fn legal_moves_no_castling_through_check() {
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("5r1k/8/8/8/8/8/8/R3K2R w KQ - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        
+        let castles: Vec<_> = gen.generate_legal(&pos)
+            .into_iter()
+            .filter(|m| m.kind() == MoveType::Castling)
+            .collect();
+        assert_eq!(castles.len(), 1);
+        assert_eq!(castles[0].dst(), Square::from_rank_file(0, 2, &dims));
+    }
+    
+    #[test]
+    fn legal_moves_en_passant_discovered_check() {
+        let dims = Dimensions::standard();
+        // capturing on d6 would clear the fifth rank between the king and the rook
+        let pos: Position<BitBoard64> = Fen::parse("7k/8/8/K2pP2r/8/8/8/8 w - d6 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        
+        assert!(gen.generate_pseudo_legal(&pos).iter().any(|m| m.kind() == MoveType::EnPassant));
+        assert!(gen.generate_legal(&pos).iter().all(|m| m.kind() != MoveType::EnPassant));
+    }
+    
+    #[test]
+    fn legal_moves_custom_pattern_check_on_large_board() {
+        use crate::movegen::patterns::{Direction, SlidingPattern};
+        
+        let dims = Dimensions::new(10, 10);
+        let mut pos: Position<BitBoard256> = Position::new_empty(dims);
+        let king_sq = Square::from_rank_file(0, 0, &dims);
+        let custom_sq = Square::from_rank_file(9, 9, &dims);
+        pos.set_piece(king_sq, Piece { color: Color::White, kind: PieceKind::King });
+        pos.set_piece(custom_sq, Piece { color: Color::Black, kind: PieceKind::Custom(0) });
+        
+        let mut gen = MoveGenerator::new(dims);
+        gen.register_custom_pattern(
+            PieceKind::Custom(0),
+            Box::new(SlidingPattern::new(Direction::BISHOP_DIRS.to_vec())),
+        );
+        
+        assert!(gen.in_check(&pos));
+        let b2 = Square::from_rank_file(1, 1, &dims);
+        let moves = gen.generate_legal(&pos);
+        assert_eq!(moves.len(), 2);
+        assert!(moves.iter().all(|m| m.dst() != b2));
+    }
+    
+    #[test]
+    fn staged_generation_with_drops_and_custom_patterns() {
+        use crate::movegen::patterns::{Direction, SlidingPattern};
+        
+        let dims = Dimensions::standard();
+        let mut gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+        gen.register_custom_pattern(
+            PieceKind::Custom(0),
+            Box::new(SlidingPattern::new(Direction::ROOK_DIRS.to_vec())),
+        );
+        
+        // drops and castling are quiet, promotions without a capture are captures
+        let pos: Position<BitBoard64> = Fen::parse("r3k3/1P6/8/8/8/8/8/4K2R[N] w K - 0 1", dims).unwrap();
+        let captures = gen.generate(&pos, GenType::Captures);
+        assert_eq!(captures.len(), 8);
+        assert!(captures.iter().all(|m| m.kind() == MoveType::Promotion));
+        let quiets = gen.generate(&pos, GenType::Quiets);
+        assert_eq!(quiets.iter().filter(|m| m.kind() == MoveType::Drop).count(), 59);
+        assert_eq!(quiets.iter().filter(|m| m.kind() == MoveType::Castling).count(), 1);
+        
+        // a rook checker leaves the king, the capture and drops between the two
+        let pos: Position<BitBoard64> = Fen::parse("4r2k/8/8/8/8/8/8/4K2R[N] w K - 0 1", dims).unwrap();
+        let evasions = gen.generate(&pos, GenType::Evasions);
+        assert_eq!(evasions.iter().filter(|m| m.kind() == MoveType::Drop).count(), 6);
+        assert!(evasions.iter().all(|m| m.kind() != MoveType::Castling));
+        assert_eq!(evasions.iter().filter(|m| m.src() == Square::parse("h1", &dims).unwrap()).count(), 0);
+        
+        // a custom checker keeps every move but castling
+        let mut pos = pos;
+        let e8 = Square::parse("e8", &dims).unwrap();
+        pos.set_piece(e8, Piece { color: Color::Black, kind: PieceKind::Custom(0) });
+        let evasions = gen.generate(&pos, GenType::Evasions);
+        let all = gen.generate(&pos, GenType::All);
+        assert_eq!(evasions.len(), all.len() - 1);
+    }
+    
+    #[test]
+    fn attackers_to_covers_every_kind_and_asymmetric_patterns() {
+        use crate::movegen::patterns::{Direction, JumpingPattern};
+        
+        let dims = Dimensions::standard();
+        let sq = |text| Square::parse(text, &dims).unwrap();
+        let mut gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+        // moves two squares forward for White only
+        gen.register_custom_pattern(
+            PieceKind::Custom(0),
+            Box::new(JumpingPattern::new(vec![Direction { file_delta: 0, rank_delta: 2 }])),
+        );
+        
+        let mut pos: Position<BitBoard64> = Fen::parse("4k3/8/8/2n5/4P3/8/1B6/R3K2R w - - 0 1", dims).unwrap();
+        let custom = Piece { color: Color::White, kind: PieceKind::Custom(0) };
+        pos.set_piece(sq("d3"), custom);
+        pos.set_piece(sq("d7"), custom);
+        
+        let attackers = gen.attackers_to(&pos, sq("d5"), Color::White);
+        assert_eq!(attackers, BitBoard64::empty_for_dims(&dims).set(sq("d3")).set(sq("e4")));
+        // the pattern only leads forward, so d3 does not attack d1 although d1 reaches d3
+        let attackers = gen.attackers_to(&pos, sq("d1"), Color::White);
+        assert!(attackers.contains(sq("a1")) && attackers.contains(sq("e1")));
+        assert!(!attackers.contains(sq("d3")));
+        assert_eq!(gen.attackers_to(&pos, sq("e4"), Color::Black), BitBoard64::empty_for_dims(&dims).set(sq("c5")));
+        
+        // defenders of a friendly piece count too
+        pos.set_piece(sq("d5"), Piece { color: Color::White, kind: PieceKind::Knight });
+        assert_eq!(gen.attackers_to(&pos, sq("d5"), Color::White).count(), 2);
+        
+        // x-rays once the front rook is taken off the occupancy
+        assert!(!gen.attackers_to(&pos, sq("c1"), Color::White).contains(sq("h1")));
+        let occupied = pos.blockers().clear(sq("e1"));
+        assert!(gen.attackers_to_with(&pos, sq("c1"), Color::White, occupied).contains(sq("h1")));
+        
+        pos.side_to_move = Color::Black;
+        assert!(gen.checkers(&pos).is_empty());
+        pos.set_piece(sq("e6"), custom);
+        assert_eq!(gen.checkers(&pos), BitBoard64::empty_for_dims(&dims).set(sq("e6")));
+    }
+    
+    #[test]
+    fn hoppers_from_the_fen_registry() {
+        use crate::movegen::patterns::{Direction, HopperPattern};
+        use crate::piece::PieceDef;
+        
+        let dims = Dimensions::standard();
+        let sq = |text: &str| Square::parse(text, &dims).unwrap();
+        let mut registry = PieceRegistry::standard();
+        registry.register(
+            PieceDef::new(PieceKind::Custom(1), "cannon", "C")
+                .with_pattern(Box::new(HopperPattern::cannon(Direction::ROOK_DIRS.to_vec()))),
+        ).unwrap();
+        registry.register(
+            PieceDef::new(PieceKind::Custom(2), "grasshopper", "G")
+                .with_pattern(Box::new(HopperPattern::grasshopper(Direction::QUEEN_DIRS.to_vec()))),
+        ).unwrap();
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::with_registry(dims, registry);
+        let targets = |pos: &Position<BitBoard64>, from: &str| {
+            let mut dsts: Vec<_> = gen.generate_pseudo_legal(pos).into_iter()
+                .filter(|m| m.src() == sq(from))
+                .map(|m| (m.dst().to_string(&dims), m.kind()))
+                .collect();
+            dsts.sort_by(|a, b| a.0.cmp(&b.0));
+            dsts
+        };
+        
+        // the cannon slides to move and captures over exactly one screen
+        let pos = Fen::parse_with("4k3/8/n7/8/8/P7/8/C2K3b w - - 0 1", dims, gen.registry()).unwrap();
+        assert_eq!(targets(&pos, "a1"), [
+            ("a2".to_string(), MoveType::Quiet),
+            ("a6".to_string(), MoveType::Capture),
+            ("b1".to_string(), MoveType::Quiet),
+            ("c1".to_string(), MoveType::Quiet),
+            ("h1".to_string(), MoveType::Capture),
+        ]);
+        assert!(gen.is_square_attacked(&pos, sq("f1"), Color::White));
+        assert!(!gen.is_square_attacked(&pos, sq("a2"), Color::White));
+        
+        // the grasshopper lands right behind the hurdle of either color
+        let pos = Fen::parse_with("4k3/8/8/8/3n4/P1p5/8/GK6 w - - 0 1", dims, gen.registry()).unwrap();
+        assert_eq!(targets(&pos, "a1"), [
+            ("a4".to_string(), MoveType::Quiet),
+            ("c1".to_string(), MoveType::Quiet),
+            ("d4".to_string(), MoveType::Capture),
+        ]);
+        
+        // a cannon check is answered by moving the screen away
+        let pos = Fen::parse_with("k3c3/8/8/8/4N3/8/8/4K3 w - - 0 1", dims, gen.registry()).unwrap();
+        assert!(gen.in_check(&pos));
+        let legal = gen.generate_legal(&pos);
+        assert_eq!(legal.len(), 12);
+        assert_eq!(legal.iter().filter(|m| m.src() == sq("e4")).count(), 8);
+        let evasions: Vec<_> = gen.generate(&pos, GenType::Evasions).into_iter()
+            .filter(|&m| gen.is_legal(&pos, m))
+            .collect();
+        assert_eq!(evasions, legal);
+    }
+    
+    #[test]
+    fn divergent_patterns_follow_the_owner() {
+        use crate::movegen::patterns::DivergentPattern;
+        
+        let dims = Dimensions::standard();
+        let sq = |text: &str| Square::parse(text, &dims).unwrap();
+        let mut gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+        gen.register_custom_pattern(PieceKind::Custom(3), Box::new(DivergentPattern::berolina_pawn()));
+        let berolina = |color| Piece { color, kind: PieceKind::Custom(3) };
+        
+        let mut pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/8/4K3 b - - 0 1", dims).unwrap();
+        pos.set_piece(sq("d7"), berolina(Color::Black));
+        pos.set_piece(sq("d6"), Piece { color: Color::White, kind: PieceKind::Knight });
+        pos.set_piece(sq("e6"), Piece { color: Color::White, kind: PieceKind::Rook });
+        let mut moves: Vec<_> = gen.generate_pseudo_legal(&pos).into_iter()
+            .filter(|m| m.src() == sq("d7"))
+            .map(|m| (m.dst().to_string(&dims), m.kind()))
+            .collect();
+        moves.sort_by(|a, b| a.0.cmp(&b.0));
+        // diagonal moves only onto empty squares, the straight capture only onto a piece
+        assert_eq!(moves, [
+            ("b5".to_string(), MoveType::Quiet),
+            ("c6".to_string(), MoveType::Quiet),
+            ("d6".to_string(), MoveType::Capture),
+        ]);
+        
+        // it attacks straight ahead towards its own side's front
+        pos.set_piece(sq("d2"), berolina(Color::White));
+        assert!(gen.attackers_to(&pos, sq("d3"), Color::White).contains(sq("d2")));
+        assert!(!gen.attackers_to(&pos, sq("e3"), Color::White).contains(sq("d2")));
+        assert!(gen.attackers_to(&pos, sq("d6"), Color::Black).contains(sq("d7")));
+    }
+    
+    #[test]
+    fn custom_kind_without_pattern_has_no_moves() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
+        let sq = Square::from_rank_file(3, 3, &dims);
+        pos.set_piece(sq, Piece { color: Color::White, kind: PieceKind::Custom(13) });
+        pos.set_piece(Square::from_rank_file(5, 4, &dims), Piece { color: Color::Black, kind: PieceKind::King });
+        
+        let gen = MoveGenerator::new(dims);
+        assert!(gen.generate_pseudo_legal(&pos).is_empty());
+        
+        pos.side_to_move = Color::Black;
+        assert!(!gen.in_check(&pos));
+    }
+    
+    #[test]
+    fn drops_follow_drop_rules_and_block_checks() {
+        use crate::movegen::DropRules;
+        
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/8/4K3[Pn] w - - 0 1", dims).unwrap();
+        let mut gen = MoveGenerator::new(dims);
+        
+        let drops = |gen: &MoveGenerator<BitBoard64>, pos: &Position<BitBoard64>| {
+            gen.generate_legal(pos).into_iter().filter(|m| m.kind() == MoveType::Drop).count()
+        };
+        assert_eq!(drops(&gen, &pos), 62);
+        gen.set_drop_rules(DropRules::crazyhouse());
+        assert_eq!(drops(&gen, &pos), 48);
+        
+        // in check from the rook, only drops onto the e-file between rook and king are legal
+        let pos: Position<BitBoard64> = Fen::parse("4r2k/8/8/8/8/8/8/4K3[N] w - - 0 1", dims).unwrap();
+        let blocks: Vec<_> = gen.generate_legal(&pos).into_iter().filter(|m| m.kind() == MoveType::Drop).collect();
+        assert_eq!(blocks.len(), 6);
+        assert!(blocks.iter().all(|m| m.dst().file_rank(&dims).0 == 4));
+    }
+    
+    #[test]
+    fn move_generation_order_is_deterministic() {
+        let dims = Dimensions::standard();
+        let fen_str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+        
+        let first = gen.generate_pseudo_legal(&Fen::parse(fen_str, dims).unwrap());
+        for _ in 0..4 {
+            let again = gen.generate_pseudo_legal(&Fen::parse(fen_str, dims).unwrap());
+            assert_eq!(first, again);
+        }
+    }
+    
+    #[test]
+    fn generate_into_appends_to_move_list() {
+        let dims = Dimensions::new(10, 10);
+        let pos: Position<BitBoard256> =
+            Fen::parse("r3k4r/pppppppppp/10/10/4q5/10/10/10/PPPP1PPPPP/R3K4R w KQkq - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        let marker = Move::new(Square(0), Square(1), MoveType::Quiet);
+
+        let mut list = MoveList::new();
+        list.push(marker);
+        gen.generate_pseudo_legal_into(&pos, &mut list);
+        assert_eq!(list[0], marker);
+        assert_eq!(&list[1..], gen.generate_pseudo_legal(&pos).as_slice());
+
+        // moves already in the list are not filtered
+        list.truncate(1);
+        gen.generate_legal_into(&pos, &mut list);
+        assert_eq!(list[0], marker);
+        assert_eq!(&list[1..], gen.generate_legal(&pos).as_slice());
+        assert!(list.len() - 1 < gen.generate_pseudo_legal(&pos).len());
+    }
+
+    #[test]
+    fn move_list_spills_past_max_moves() {
+        use crate::movegen::MAX_MOVES;
+        
+        let dims = Dimensions::new(16, 16);
+        let mut pos: Position<BitBoard256> = Position::new_empty(dims);
+        pos.set_piece(Square(0), Piece { color: Color::White, kind: PieceKind::King });
+        pos.set_piece(Square(255), Piece { color: Color::Black, kind: PieceKind::King });
+        let standard = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen];
+        for kind in standard.into_iter().chain((0..4).map(PieceKind::Custom)) {
+            pos.add_to_pocket(Color::White, kind);
+        }
+        let gen = MoveGenerator::new(dims);
+        
+        let mut list = MoveList::new();
+        gen.generate_pseudo_legal_into(&pos, &mut list);
+        let drops = list.iter().filter(|m| m.kind() == MoveType::Drop).count();
+        assert!(list.len() > MAX_MOVES);
+        assert_eq!(drops, 254 * 9);
+        assert_eq!(list.to_vec(), list.clone().into_iter().collect::<Vec<_>>());
     }
-}
 
+    #[test]
+    fn walls_block_movement_and_castling() {
+        let dims = Dimensions::standard();
+        // walls on a3, b3 and f1
+        let pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/..6/1P6/RN2K.1R w K - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        let moves = gen.generate_legal(&pos);
+        
+        assert!(moves.iter().all(|m| !pos.is_wall(m.dst())));
+        assert!(moves.iter().all(|m| m.kind() != MoveType::Castling));
+        
+        let b2 = Square::from_rank_file(1, 1, &dims);
+        assert!(moves.iter().all(|m| m.src() != b2));
+        
+        let a1 = Square::from_rank_file(0, 0, &dims);
+        let rook_moves: Vec<_> = moves.iter().filter(|m| m.src() == a1).collect();
+        assert_eq!(rook_moves.len(), 1);
+        assert_eq!(rook_moves[0].dst(), Square::from_rank_file(1, 0, &dims));
+        
+        let b1 = Square::from_rank_file(0, 1, &dims);
+        assert_eq!(moves.iter().filter(|m| m.src() == b1).count(), 2);
+    }
+    
+    #[test]
+    fn walls_block_custom_patterns_and_attacks() {
+        use crate::movegen::patterns::{Direction, JumpingPattern, SlidingPattern};
+        
+        let dims = Dimensions::new(10, 10);
+        let mut pos: Position<BitBoard256> = Position::new_empty(dims);
+        let rook_sq = Square::from_rank_file(0, 0, &dims);
+        let king_sq = Square::from_rank_file(9, 0, &dims);
+        let wall_sq = Square::from_rank_file(4, 0, &dims);
+        pos.set_piece(rook_sq, Piece { color: Color::White, kind: PieceKind::Custom(0) });
+        pos.set_piece(Square::from_rank_file(2, 2, &dims), Piece { color: Color::White, kind: PieceKind::Custom(1) });
+        pos.set_piece(king_sq, Piece { color: Color::Black, kind: PieceKind::King });
+        pos.set_wall(wall_sq);
+        pos.set_wall(Square::from_rank_file(3, 3, &dims));
+        
+        let mut gen = MoveGenerator::new(dims);
+        gen.register_custom_pattern(PieceKind::Custom(0), Box::new(SlidingPattern::new(Direction::ROOK_DIRS.to_vec())));
+        gen.register_custom_pattern(PieceKind::Custom(1), Box::new(JumpingPattern::new(Direction::BISHOP_DIRS.to_vec())));
+        
+        let moves = gen.generate_pseudo_legal(&pos);
+        assert!(moves.iter().all(|m| !pos.is_wall(m.dst())));
+        assert_eq!(moves.iter().filter(|m| m.src() == rook_sq).count(), 3 + 9);
+        assert!(!gen.is_square_attacked(&pos, king_sq, Color::White));
+        
+        pos.clear_wall(wall_sq);
+        assert!(gen.is_square_attacked(&pos, king_sq, Color::White));
+    }
+}
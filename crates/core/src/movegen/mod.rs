@@ -2,8 +2,15 @@ pub mod patterns;
 pub mod standard;
 pub mod generator;
 pub mod attack_table;
+pub mod drops;
+pub mod move_list;
 
-pub use generator::MoveGenerator;
-pub use patterns::{MovePattern, SlidingPattern, JumpingPattern};
+pub use generator::{GenType, MoveGenerator};
+pub use patterns::{
+    MovePattern, SlidingPattern, JumpingPattern, HopperPattern, LameLeaperPattern, RiderPattern,
+    DivergentPattern, Leg, LegMode, CompoundPattern,
+};
 pub use attack_table::AttackTable;
+pub use drops::DropRules;
+pub use move_list::{MoveList, MAX_MOVES};
 
@@ -0,0 +1,170 @@
+use std::ops::{Deref, DerefMut};
+
+use arrayvec::ArrayVec;
+
+use crate::moves::Move;
+
+/// Moves a `MoveList` keeps on the stack. Longer lists, such as every empty square
+/// of a 16x16 board as a drop target for several pocket kinds, move to the heap.
+pub const MAX_MOVES: usize = 2048;
+
+/// Move buffer kept on the stack up to `MAX_MOVES` moves, so move generation in perft
+/// and search does not allocate; past that it spills into a `Vec`. Derefs to a slice
+/// for iteration, sorting and indexing.
+#[derive(Debug, Clone)]
+pub struct MoveList {
+    moves: Storage,
+}
+
+// the inline buffer is the point: it keeps generation off the heap
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+enum Storage {
+    Inline(ArrayVec<Move, MAX_MOVES>),
+    Heap(Vec<Move>),
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self { moves: Storage::Inline(ArrayVec::new()) }
+    }
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn push(&mut self, mv: Move) {
+        match &mut self.moves {
+            Storage::Inline(moves) => {
+                if let Err(full) = moves.try_push(mv) {
+                    let mut spilled = Vec::with_capacity(2 * MAX_MOVES);
+                    spilled.extend_from_slice(moves);
+                    spilled.push(full.element());
+                    self.moves = Storage::Heap(spilled);
+                }
+            }
+            Storage::Heap(moves) => moves.push(mv),
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        match &mut self.moves {
+            Storage::Inline(moves) => moves.clear(),
+            Storage::Heap(moves) => moves.clear(),
+        }
+    }
+
+    /// Keep the first `len` moves
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        match &mut self.moves {
+            Storage::Inline(moves) => moves.truncate(len),
+            Storage::Heap(moves) => moves.truncate(len),
+        }
+    }
+
+    /// Remove the move at `idx`, replacing it with the last one
+    #[inline]
+    pub fn swap_remove(&mut self, idx: usize) -> Move {
+        match &mut self.moves {
+            Storage::Inline(moves) => moves.swap_remove(idx),
+            Storage::Heap(moves) => moves.swap_remove(idx),
+        }
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&mut Move) -> bool) {
+        match &mut self.moves {
+            Storage::Inline(moves) => moves.retain(keep),
+            Storage::Heap(moves) => moves.retain_mut(keep),
+        }
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    #[inline]
+    fn deref(&self) -> &[Move] {
+        match &self.moves {
+            Storage::Inline(moves) => moves,
+            Storage::Heap(moves) => moves,
+        }
+    }
+}
+
+impl DerefMut for MoveList {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [Move] {
+        match &mut self.moves {
+            Storage::Inline(moves) => moves,
+            Storage::Heap(moves) => moves,
+        }
+    }
+}
+
+/// Owning iterator over a `MoveList`
+#[allow(clippy::large_enum_variant)]
+pub enum IntoIter {
+    Inline(arrayvec::IntoIter<Move, MAX_MOVES>),
+    Heap(std::vec::IntoIter<Move>),
+}
+
+impl Iterator for IntoIter {
+    type Item = Move;
+
+    #[inline]
+    fn next(&mut self) -> Option<Move> {
+        match self {
+            IntoIter::Inline(moves) => moves.next(),
+            IntoIter::Heap(moves) => moves.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IntoIter::Inline(moves) => moves.size_hint(),
+            IntoIter::Heap(moves) => moves.size_hint(),
+        }
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.moves {
+            Storage::Inline(moves) => IntoIter::Inline(moves.into_iter()),
+            Storage::Heap(moves) => IntoIter::Heap(moves.into_iter()),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Extend<Move> for MoveList {
+    fn extend<I: IntoIterator<Item = Move>>(&mut self, iter: I) {
+        for mv in iter {
+            self.push(mv);
+        }
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
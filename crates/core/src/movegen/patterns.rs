@@ -1,4 +1,7 @@
-use crate::board::{Dimensions, Square, BitBoard, BB};
+use crate::board::{Dimensions, Square, BB};
+use crate::movegen::standard::StandardPatterns;
+use crate::piece::{Color, PieceKind};
+use crate::movegen::attack_table::AttackTable;
 
 /// Direction offsets for sliding pieces
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -41,11 +44,47 @@ impl Direction {
         Direction::SOUTHEAST,
         Direction::SOUTHWEST,
     ];
+    
+    pub const KNIGHT_LEAPS: &'static [Direction] = &[
+        Direction { file_delta: 1, rank_delta: 2 },
+        Direction { file_delta: 2, rank_delta: 1 },
+        Direction { file_delta: 2, rank_delta: -1 },
+        Direction { file_delta: 1, rank_delta: -2 },
+        Direction { file_delta: -1, rank_delta: -2 },
+        Direction { file_delta: -2, rank_delta: -1 },
+        Direction { file_delta: -2, rank_delta: 1 },
+        Direction { file_delta: -1, rank_delta: 2 },
+    ];
+    
+    /// Square `self` away from `(file, rank)`, or `None` off the board
+    fn offset(self, file: i8, rank: i8, dims: &Dimensions) -> Option<(i8, i8)> {
+        let (file, rank) = (file + self.file_delta, rank + self.rank_delta);
+        if file < 0 || file >= dims.width as i8 || rank < 0 || rank >= dims.height as i8 {
+            return None;
+        }
+        Some((file, rank))
+    }
 }
 
 
-pub trait MovePattern {
-    fn attacks_from(&self, sq: Square, dims: &Dimensions, occupied: BitBoard, friendly: BitBoard) -> BitBoard;
+/// Where a piece may go from a square. `color` owns the piece, so oriented patterns
+/// know which way is forward; the others ignore it. `occupied` holds every square
+/// that blocks movement (pieces and walls), `friendly` every square the piece may
+/// not land on (its own pieces and walls).
+pub trait MovePattern<B: BB> {
+    fn attacks_from(&self, sq: Square, color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B;
+
+    /// Same as `attacks_from`, but may use the precomputed tables of `table`
+    fn attacks_with_table(&self, table: &AttackTable<B>, sq: Square, color: Color, occupied: B, friendly: B) -> B {
+        self.attacks_from(sq, color, table.dims(), occupied, friendly)
+    }
+
+    /// Squares the piece may move to without capturing, for patterns where they
+    /// differ from the attacked squares; only the empty ones are used. `None` means
+    /// the piece moves where it attacks.
+    fn quiet_moves_from(&self, _sq: Square, _color: Color, _dims: &Dimensions, _occupied: B, _friendly: B) -> Option<B> {
+        None
+    }
 }
 
 pub struct SlidingPattern {
@@ -69,10 +108,17 @@ impl SlidingPattern {
     }
 }
 
-impl MovePattern for SlidingPattern {
-    fn attacks_from(&self, sq: Square, dims: &Dimensions, occupied: BitBoard, friendly: BitBoard) -> BitBoard {
+impl<B: BB> MovePattern<B> for SlidingPattern {
+    fn attacks_with_table(&self, table: &AttackTable<B>, sq: Square, color: Color, occupied: B, friendly: B) -> B {
+        match table.slider_attacks(sq, occupied, &self.directions, self.max_distance) {
+            Some(attacks) => attacks.difference(friendly),
+            None => self.attacks_from(sq, color, table.dims(), occupied, friendly),
+        }
+    }
+
+    fn attacks_from(&self, sq: Square, _color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B {
         let (file, rank) = sq.file_rank(dims);
-        let mut attacks = BitBoard::empty();
+        let mut attacks = B::empty_for_dims(dims);
         
         for &dir in &self.directions {
             let mut current_file = file as i8;
@@ -126,10 +172,10 @@ impl JumpingPattern {
     }
 }
 
-impl MovePattern for JumpingPattern {
-    fn attacks_from(&self, sq: Square, dims: &Dimensions, _occupied: BitBoard, friendly: BitBoard) -> BitBoard {
+impl<B: BB> MovePattern<B> for JumpingPattern {
+    fn attacks_from(&self, sq: Square, _color: Color, dims: &Dimensions, _occupied: B, friendly: B) -> B {
         let (file, rank) = sq.file_rank(dims);
-        let mut attacks = BitBoard::empty();
+        let mut attacks = B::empty_for_dims(dims);
         
         for &offset in &self.offsets {
             let target_file = file as i8 + offset.file_delta;
@@ -153,3 +199,614 @@ impl MovePattern for JumpingPattern {
     }
 }
 
+
+/// A piece that needs a hurdle: it travels along each direction to the first
+/// occupied square, hops over it and lands beyond, up to `max_landing` squares
+/// and never past the next piece. Quiet moves either hop the same way or, for a
+/// cannon, slide up to the first piece.
+pub struct HopperPattern {
+    directions: Vec<Direction>,
+    max_landing: Option<u8>, // None = up to the next piece
+    slides_quietly: bool,
+}
+
+impl HopperPattern {
+    /// Moves and captures by hopping along `directions`
+    pub fn new(directions: Vec<Direction>, max_landing: Option<u8>) -> Self {
+        Self {
+            directions,
+            max_landing,
+            slides_quietly: false,
+        }
+    }
+
+    /// Xiangqi cannon: slides to move, captures the first piece beyond a screen
+    pub fn cannon(directions: Vec<Direction>) -> Self {
+        Self {
+            directions,
+            max_landing: None,
+            slides_quietly: true,
+        }
+    }
+
+    /// Grasshopper: lands on the square right behind the hurdle
+    pub fn grasshopper(directions: Vec<Direction>) -> Self {
+        Self::new(directions, Some(1))
+    }
+}
+
+impl<B: BB> MovePattern<B> for HopperPattern {
+    fn attacks_from(&self, sq: Square, _color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B {
+        let (file, rank) = sq.file_rank(dims);
+        let mut attacks = B::empty_for_dims(dims);
+
+        for &dir in &self.directions {
+            let mut current = (file as i8, rank as i8);
+            let mut hopped = false;
+            let mut distance = 0;
+
+            while let Some((current_file, current_rank)) = dir.offset(current.0, current.1, dims) {
+                current = (current_file, current_rank);
+                let target_sq = Square::from_rank_file(current_rank as u8, current_file as u8, dims);
+
+                if !hopped {
+                    hopped = occupied.contains(target_sq);
+                    continue;
+                }
+
+                if self.max_landing.is_some_and(|max| distance >= max) {
+                    break;
+                }
+                distance += 1;
+
+                if occupied.contains(target_sq) {
+                    if !friendly.contains(target_sq) {
+                        attacks = attacks.set(target_sq);
+                    }
+                    break;
+                }
+                attacks = attacks.set(target_sq);
+            }
+        }
+
+        attacks
+    }
+
+    fn quiet_moves_from(&self, sq: Square, _color: Color, dims: &Dimensions, occupied: B, _friendly: B) -> Option<B> {
+        if !self.slides_quietly {
+            return None;
+        }
+
+        let (file, rank) = sq.file_rank(dims);
+        let mut moves = B::empty_for_dims(dims);
+        for &dir in &self.directions {
+            let mut current = (file as i8, rank as i8);
+            while let Some((current_file, current_rank)) = dir.offset(current.0, current.1, dims) {
+                current = (current_file, current_rank);
+                let target_sq = Square::from_rank_file(current_rank as u8, current_file as u8, dims);
+                if occupied.contains(target_sq) {
+                    break;
+                }
+                moves = moves.set(target_sq);
+            }
+        }
+        Some(moves)
+    }
+}
+
+/// Leaps that are blocked when a square on the way is occupied, like the Xiangqi
+/// horse and elephant. Each leap lists the offsets, from the square it starts on,
+/// that must be empty.
+pub struct LameLeaperPattern {
+    leaps: Vec<(Direction, Vec<Direction>)>,
+}
+
+impl LameLeaperPattern {
+    /// Leaps blocked on the king-step path that goes straight before turning
+    /// diagonally: `(1, 2)` by `(0, 1)`, `(2, 2)` by `(1, 1)`
+    pub fn new(leaps: Vec<Direction>) -> Self {
+        let leaps = leaps
+            .into_iter()
+            .map(|leap| {
+                let mut blocks = Vec::new();
+                let (mut file, mut rank) = (leap.file_delta, leap.rank_delta);
+                loop {
+                    let longest = file.abs().max(rank.abs());
+                    let step = |delta: i8| if delta.abs() == longest { delta.signum() } else { 0 };
+                    let (file_step, rank_step) = (step(file), step(rank));
+                    file -= file_step;
+                    rank -= rank_step;
+                    if file == 0 && rank == 0 {
+                        break;
+                    }
+                    blocks.push(Direction {
+                        file_delta: leap.file_delta - file,
+                        rank_delta: leap.rank_delta - rank,
+                    });
+                }
+                (leap, blocks)
+            })
+            .collect();
+        Self { leaps }
+    }
+
+    /// Leaps with explicit blocking offsets
+    pub fn with_blocks(leaps: Vec<(Direction, Vec<Direction>)>) -> Self {
+        Self { leaps }
+    }
+
+    /// Xiangqi horse: a knight blocked by the orthogonal neighbour it passes
+    pub fn xiangqi_horse() -> Self {
+        Self::new(Direction::KNIGHT_LEAPS.to_vec())
+    }
+
+    /// Xiangqi elephant: two squares diagonally, blocked by the square in between
+    pub fn xiangqi_elephant() -> Self {
+        let leaps = Direction::BISHOP_DIRS
+            .iter()
+            .map(|dir| Direction { file_delta: 2 * dir.file_delta, rank_delta: 2 * dir.rank_delta })
+            .collect();
+        Self::new(leaps)
+    }
+}
+
+impl<B: BB> MovePattern<B> for LameLeaperPattern {
+    fn attacks_from(&self, sq: Square, _color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B {
+        let (file, rank) = sq.file_rank(dims);
+        let (file, rank) = (file as i8, rank as i8);
+        let mut attacks = B::empty_for_dims(dims);
+
+        for (leap, blocks) in &self.leaps {
+            let Some((target_file, target_rank)) = leap.offset(file, rank, dims) else { continue };
+            // blocking squares lie between start and target, so they are on the board
+            let blocked = blocks.iter().any(|block| {
+                block.offset(file, rank, dims).is_some_and(|(block_file, block_rank)| {
+                    occupied.contains(Square::from_rank_file(block_rank as u8, block_file as u8, dims))
+                })
+            });
+            let target_sq = Square::from_rank_file(target_rank as u8, target_file as u8, dims);
+            if !blocked && !friendly.contains(target_sq) {
+                attacks = attacks.set(target_sq);
+            }
+        }
+
+        attacks
+    }
+}
+
+/// Repeats each leap in a straight line until it leaves the board or meets a piece,
+/// like the nightrider. Leaps need not be unit steps; squares skipped by a leap do
+/// not block it.
+pub struct RiderPattern {
+    leaps: Vec<Direction>,
+    max_distance: Option<u8>, // leaps per move, None = unlimited
+}
+
+impl RiderPattern {
+    pub fn new(leaps: Vec<Direction>) -> Self {
+        Self {
+            leaps,
+            max_distance: None,
+        }
+    }
+
+    pub fn with_max_distance(leaps: Vec<Direction>, max_distance: u8) -> Self {
+        Self {
+            leaps,
+            max_distance: Some(max_distance),
+        }
+    }
+
+    /// Nightrider: repeated knight leaps
+    pub fn nightrider() -> Self {
+        Self::new(Direction::KNIGHT_LEAPS.to_vec())
+    }
+}
+
+impl<B: BB> MovePattern<B> for RiderPattern {
+    fn attacks_from(&self, sq: Square, _color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B {
+        let (file, rank) = sq.file_rank(dims);
+        let mut attacks = B::empty_for_dims(dims);
+
+        for &leap in &self.leaps {
+            if leap.file_delta == 0 && leap.rank_delta == 0 {
+                continue;
+            }
+            let mut current = (file as i8, rank as i8);
+            let mut distance = 0;
+
+            while let Some((current_file, current_rank)) = leap.offset(current.0, current.1, dims) {
+                if self.max_distance.is_some_and(|max| distance >= max) {
+                    break;
+                }
+                distance += 1;
+                current = (current_file, current_rank);
+
+                let target_sq = Square::from_rank_file(current_rank as u8, current_file as u8, dims);
+                if occupied.contains(target_sq) {
+                    if !friendly.contains(target_sq) {
+                        attacks = attacks.set(target_sq);
+                    }
+                    break;
+                }
+                attacks = attacks.set(target_sq);
+            }
+        }
+
+        attacks
+    }
+}
+
+/// What a leg of a `DivergentPattern` may do on the squares it reaches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegMode {
+    Move,
+    Capture,
+    Both,
+}
+
+/// One line of a `DivergentPattern`, seen from White: Black's legs are mirrored
+/// so that a positive `rank_delta` always points away from the owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leg {
+    pub direction: Direction,
+    pub mode: LegMode,
+    pub max_distance: Option<u8>, // None = unlimited
+    /// Only usable from this rank, counted from the owner's back rank
+    pub from_rank: Option<u8>,
+}
+
+impl Leg {
+    /// A single step or leap
+    pub fn step(direction: Direction, mode: LegMode) -> Self {
+        Self {
+            direction,
+            mode,
+            max_distance: Some(1),
+            from_rank: None,
+        }
+    }
+
+    /// Repeats `direction` until blocked
+    pub fn slide(direction: Direction, mode: LegMode) -> Self {
+        Self {
+            max_distance: None,
+            ..Self::step(direction, mode)
+        }
+    }
+
+    pub fn with_max_distance(self, max_distance: u8) -> Self {
+        Self {
+            max_distance: Some(max_distance),
+            ..self
+        }
+    }
+
+    pub fn from_rank(self, rank: u8) -> Self {
+        Self {
+            from_rank: Some(rank),
+            ..self
+        }
+    }
+}
+
+/// Legs oriented by the owner's side that may each move, capture or both, such as
+/// pawns, Berolina pawns or Shogi generals. Legs stop at the first occupied square.
+pub struct DivergentPattern {
+    legs: Vec<Leg>,
+}
+
+impl DivergentPattern {
+    pub fn new(legs: Vec<Leg>) -> Self {
+        Self { legs }
+    }
+
+    /// Standard pawn without en passant and promotion, which the generator adds
+    /// for `PieceKind::Pawn`
+    pub fn pawn() -> Self {
+        Self::new(vec![
+            Leg::step(Direction::NORTH, LegMode::Move),
+            Leg::step(Direction::NORTH, LegMode::Move).with_max_distance(2).from_rank(1),
+            Leg::step(Direction::NORTHWEST, LegMode::Capture),
+            Leg::step(Direction::NORTHEAST, LegMode::Capture),
+        ])
+    }
+
+    /// Berolina pawn: moves diagonally forward, captures straight ahead
+    pub fn berolina_pawn() -> Self {
+        Self::new(vec![
+            Leg::step(Direction::NORTHWEST, LegMode::Move),
+            Leg::step(Direction::NORTHEAST, LegMode::Move),
+            Leg::step(Direction::NORTHWEST, LegMode::Move).with_max_distance(2).from_rank(1),
+            Leg::step(Direction::NORTHEAST, LegMode::Move).with_max_distance(2).from_rank(1),
+            Leg::step(Direction::NORTH, LegMode::Capture),
+        ])
+    }
+
+    /// Squares reached by the capturing legs, including the first occupied square
+    /// of each, or by the moving legs up to the first occupied square
+    fn walk<B: BB>(&self, sq: Square, color: Color, dims: &Dimensions, occupied: B, friendly: B, captures: bool) -> B {
+        let skipped = if captures { LegMode::Move } else { LegMode::Capture };
+        let (file, rank) = sq.file_rank(dims);
+        let relative_rank = match color {
+            Color::White => rank,
+            Color::Black => dims.height - 1 - rank,
+        };
+        let mut reached = B::empty_for_dims(dims);
+
+        for leg in &self.legs {
+            if leg.mode == skipped || leg.from_rank.is_some_and(|from| from != relative_rank) {
+                continue;
+            }
+            let dir = match color {
+                Color::White => leg.direction,
+                Color::Black => Direction { rank_delta: -leg.direction.rank_delta, ..leg.direction },
+            };
+            if dir.file_delta == 0 && dir.rank_delta == 0 {
+                continue;
+            }
+
+            let mut current = (file as i8, rank as i8);
+            let mut distance = 0;
+            while let Some((current_file, current_rank)) = dir.offset(current.0, current.1, dims) {
+                if leg.max_distance.is_some_and(|max| distance >= max) {
+                    break;
+                }
+                distance += 1;
+                current = (current_file, current_rank);
+
+                let target_sq = Square::from_rank_file(current_rank as u8, current_file as u8, dims);
+                if occupied.contains(target_sq) {
+                    if captures && !friendly.contains(target_sq) {
+                        reached = reached.set(target_sq);
+                    }
+                    break;
+                }
+                reached = reached.set(target_sq);
+            }
+        }
+
+        reached
+    }
+}
+
+impl<B: BB> MovePattern<B> for DivergentPattern {
+    fn attacks_from(&self, sq: Square, color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B {
+        self.walk(sq, color, dims, occupied, friendly, true)
+    }
+
+    fn quiet_moves_from(&self, sq: Square, color: Color, dims: &Dimensions, occupied: B, friendly: B) -> Option<B> {
+        if self.legs.iter().all(|leg| leg.mode == LegMode::Both) {
+            return None;
+        }
+        Some(self.walk(sq, color, dims, occupied, friendly, false))
+    }
+}
+
+/// Attacks of a whole compound from `AttackTable`, given the square and occupancy
+type TableLookup<B> = fn(&AttackTable<B>, Square, B) -> B;
+
+/// Moves and attacks of several patterns together, like knight and bishop for the
+/// archbishop. The presets look their attacks up in the `AttackTable` in one go.
+pub struct CompoundPattern<B> {
+    parts: Vec<Box<dyn MovePattern<B>>>,
+    table_attacks: Option<TableLookup<B>>,
+}
+
+impl<B: BB> CompoundPattern<B> {
+    pub fn new(parts: Vec<Box<dyn MovePattern<B>>>) -> Self {
+        Self {
+            parts,
+            table_attacks: None,
+        }
+    }
+
+    /// The patterns of standard kinds together, e.g. `[Knight, Rook]`
+    pub fn from_kinds(kinds: &[PieceKind]) -> Self {
+        Self::new(kinds.iter().map(|&kind| StandardPatterns::pattern_for(kind)).collect())
+    }
+
+    /// Knight and bishop, as in Capablanca and Grand chess
+    pub fn archbishop() -> Self {
+        Self {
+            table_attacks: Some(AttackTable::archbishop_attacks),
+            ..Self::from_kinds(&[PieceKind::Knight, PieceKind::Bishop])
+        }
+    }
+
+    /// Knight and rook, as in Capablanca and Grand chess
+    pub fn chancellor() -> Self {
+        Self {
+            table_attacks: Some(AttackTable::chancellor_attacks),
+            ..Self::from_kinds(&[PieceKind::Knight, PieceKind::Rook])
+        }
+    }
+
+    /// Knight and queen
+    pub fn amazon() -> Self {
+        Self {
+            table_attacks: Some(AttackTable::amazon_attacks),
+            ..Self::from_kinds(&[PieceKind::Knight, PieceKind::Queen])
+        }
+    }
+}
+
+impl<B: BB> MovePattern<B> for CompoundPattern<B> {
+    fn attacks_from(&self, sq: Square, color: Color, dims: &Dimensions, occupied: B, friendly: B) -> B {
+        self.parts.iter().fold(B::empty_for_dims(dims), |attacks, part| {
+            attacks.union(part.attacks_from(sq, color, dims, occupied, friendly))
+        })
+    }
+
+    fn attacks_with_table(&self, table: &AttackTable<B>, sq: Square, color: Color, occupied: B, friendly: B) -> B {
+        if let Some(lookup) = self.table_attacks {
+            return lookup(table, sq, occupied).difference(friendly);
+        }
+        self.parts.iter().fold(B::empty_for_dims(table.dims()), |attacks, part| {
+            attacks.union(part.attacks_with_table(table, sq, color, occupied, friendly))
+        })
+    }
+
+    fn quiet_moves_from(&self, sq: Square, color: Color, dims: &Dimensions, occupied: B, friendly: B) -> Option<B> {
+        let quiets: Vec<_> = self
+            .parts
+            .iter()
+            .map(|part| part.quiet_moves_from(sq, color, dims, occupied, friendly))
+            .collect();
+        if quiets.iter().all(Option::is_none) {
+            return None;
+        }
+
+        let moves = self.parts.iter().zip(quiets).fold(B::empty_for_dims(dims), |moves, (part, quiet)| {
+            moves.union(quiet.unwrap_or_else(|| part.attacks_from(sq, color, dims, occupied, friendly)))
+        });
+        Some(moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64};
+
+    fn squares<B: BB>(mut bb: B, dims: &Dimensions) -> Vec<String> {
+        let mut names = Vec::new();
+        while let Some(sq) = bb.pop_lsb() {
+            names.push(sq.to_string(dims));
+        }
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn lame_leapers_are_blocked_on_the_way() {
+        let dims = Dimensions::new(9, 10);
+        let sq = |text: &str| Square::parse(text, &dims).unwrap();
+        let empty = BitBoard256::empty_for_dims(&dims);
+
+        let horse = LameLeaperPattern::xiangqi_horse();
+        assert_eq!(horse.attacks_from(sq("e5"), Color::White, &dims, empty, empty).count(), 8);
+        // a piece on e6 stops both leaps towards rank 7
+        let occupied = empty.set(sq("e6"));
+        let attacks = horse.attacks_from(sq("e5"), Color::White, &dims, occupied, empty);
+        assert_eq!(attacks.count(), 6);
+        assert!(!attacks.contains(sq("d7")) && !attacks.contains(sq("f7")));
+        // the corner leaves two leaps, the edge is never wrapped
+        assert_eq!(squares(horse.attacks_from(sq("a1"), Color::White, &dims, empty, empty), &dims), ["b3", "c2"]);
+        assert_eq!(squares(horse.attacks_from(sq("i10"), Color::White, &dims, empty, empty), &dims), ["g9", "h8"]);
+
+        let elephant = LameLeaperPattern::xiangqi_elephant();
+        let occupied = empty.set(sq("d4")).set(sq("e3"));
+        let friendly = empty.set(sq("e3"));
+        assert_eq!(squares(elephant.attacks_from(sq("c5"), Color::White, &dims, occupied, friendly), &dims), ["a3", "a7", "e7"]);
+
+        let camel = LameLeaperPattern::new(vec![Direction { file_delta: 1, rank_delta: 3 }]);
+        assert_eq!(camel.leaps[0].1, [
+            Direction { file_delta: 0, rank_delta: 1 },
+            Direction { file_delta: 0, rank_delta: 2 },
+        ]);
+    }
+
+    #[test]
+    fn divergent_pawn_matches_standard_pawns() {
+        use crate::movegen::standard::StandardPatterns;
+
+        let pawn = DivergentPattern::pawn();
+        for dims in [Dimensions::standard(), Dimensions::new(5, 6), Dimensions::new(12, 10)] {
+            let empty = BitBoard256::empty_for_dims(&dims);
+            let occupied = (0..dims.num_squares()).step_by(3).fold(empty, |bb, idx| bb.set(Square(idx)));
+            for idx in 0..dims.num_squares() {
+                let sq = Square(idx);
+                for color in [Color::White, Color::Black] {
+                    assert_eq!(
+                        pawn.attacks_from(sq, color, &dims, occupied, empty),
+                        StandardPatterns::pawn_attacks(sq, color, &dims, empty),
+                    );
+                    assert_eq!(
+                        pawn.quiet_moves_from(sq, color, &dims, occupied, empty),
+                        Some(StandardPatterns::pawn_pushes(sq, color, &dims, occupied)),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compound_presets_match_their_parts() {
+        let knight = || Box::new(JumpingPattern::new(Direction::KNIGHT_LEAPS.to_vec())) as Box<dyn MovePattern<BitBoard256>>;
+        let slider = |dirs: &[Direction]| Box::new(SlidingPattern::new(dirs.to_vec())) as Box<dyn MovePattern<BitBoard256>>;
+        let cases = [
+            (CompoundPattern::archbishop(), CompoundPattern::new(vec![knight(), slider(Direction::BISHOP_DIRS)])),
+            (CompoundPattern::chancellor(), CompoundPattern::new(vec![knight(), slider(Direction::ROOK_DIRS)])),
+            (CompoundPattern::amazon(), CompoundPattern::new(vec![knight(), slider(Direction::QUEEN_DIRS)])),
+        ];
+
+        for dims in [Dimensions::new(10, 8), Dimensions::new(10, 10)] {
+            let table = AttackTable::new(dims);
+            let empty = BitBoard256::empty_for_dims(&dims);
+            let occupied = (0..dims.num_squares()).step_by(5).fold(empty, |bb, idx| bb.set(Square(idx)));
+            let friendly = (0..dims.num_squares()).step_by(10).fold(empty, |bb, idx| bb.set(Square(idx)));
+            for (preset, parts) in &cases {
+                for idx in 0..dims.num_squares() {
+                    let sq = Square(idx);
+                    let expected = parts.attacks_from(sq, Color::White, &dims, occupied, friendly);
+                    assert_eq!(preset.attacks_with_table(&table, sq, Color::White, occupied, friendly), expected);
+                    assert_eq!(preset.attacks_from(sq, Color::White, &dims, occupied, friendly), expected);
+                    assert_eq!(parts.attacks_with_table(&table, sq, Color::White, occupied, friendly), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compound_keeps_quiet_moves_of_divergent_parts() {
+        let dims = Dimensions::standard();
+        let sq = |text: &str| Square::parse(text, &dims).unwrap();
+        let empty = BitBoard64::empty_for_dims(&dims);
+
+        // a pawn that may also step sideways
+        let pattern = CompoundPattern::new(vec![
+            Box::new(DivergentPattern::pawn()),
+            Box::new(JumpingPattern::new(vec![Direction::EAST, Direction::WEST])),
+        ]);
+        let quiets = pattern.quiet_moves_from(sq("e2"), Color::White, &dims, empty, empty).unwrap();
+        assert_eq!(squares(quiets, &dims), ["d2", "e3", "e4", "f2"]);
+        let attacks = pattern.attacks_from(sq("e2"), Color::White, &dims, empty, empty);
+        assert_eq!(squares(attacks, &dims), ["d2", "d3", "f2", "f3"]);
+
+        let plain = CompoundPattern::<BitBoard64>::from_kinds(&[PieceKind::Knight, PieceKind::King]);
+        assert!(plain.quiet_moves_from(sq("e2"), Color::White, &dims, empty, empty).is_none());
+    }
+
+    #[test]
+    fn riders_repeat_leaps_up_to_max_distance() {
+        let dims = Dimensions::standard();
+        let sq = |text: &str| Square::parse(text, &dims).unwrap();
+        let empty = BitBoard64::empty_for_dims(&dims);
+
+        let nightrider = RiderPattern::nightrider();
+        assert_eq!(
+            squares(nightrider.attacks_from(sq("a1"), Color::White, &dims, empty, empty), &dims),
+            ["b3", "c2", "c5", "d7", "e3", "g4"],
+        );
+        // an enemy on c5 is captured and ends the ride, a friend is not
+        let occupied = empty.set(sq("c5")).set(sq("e3"));
+        let friendly = empty.set(sq("e3"));
+        assert_eq!(
+            squares(nightrider.attacks_from(sq("a1"), Color::White, &dims, occupied, friendly), &dims),
+            ["b3", "c2", "c5"],
+        );
+
+        let short = RiderPattern::with_max_distance(Direction::KNIGHT_LEAPS.to_vec(), 1);
+        assert_eq!(squares(short.attacks_from(sq("a1"), Color::White, &dims, empty, empty), &dims), ["b3", "c2"]);
+
+        let dims = Dimensions::new(16, 16);
+        let empty = BitBoard256::empty_for_dims(&dims);
+        let zebra_rider = RiderPattern::new(vec![Direction { file_delta: 3, rank_delta: 2 }]);
+        let from = Square::parse("a1", &dims).unwrap();
+        assert_eq!(
+            squares(zebra_rider.attacks_from(from, Color::White, &dims, empty, empty), &dims),
+            ["d3", "g5", "j7", "m9", "p11"],
+        );
+    }
+}
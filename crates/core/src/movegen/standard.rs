@@ -1,16 +1,16 @@
 use crate::{
-    board::{Dimensions, Square, BitBoard, BB},
+    board::{Dimensions, Square, BB},
     piece::{PieceKind, Color},
-    movegen::patterns::{MovePattern, SlidingPattern, JumpingPattern, Direction},
+    movegen::patterns::{MovePattern, SlidingPattern, JumpingPattern, DivergentPattern, Direction},
 };
 
 pub struct StandardPatterns;
 
 impl StandardPatterns {
-    pub fn pattern_for(kind: PieceKind) -> Box<dyn MovePattern> {
+    pub fn pattern_for<B: BB>(kind: PieceKind) -> Box<dyn MovePattern<B>> {
         match kind {
             PieceKind::Pawn => {
-                Box::new(JumpingPattern::new(vec![]))
+                Box::new(DivergentPattern::pawn())
             }
             PieceKind::Knight => {
                 Box::new(JumpingPattern::new(vec![
@@ -51,9 +51,9 @@ impl StandardPatterns {
         }
     }
     
-    pub fn pawn_attacks(sq: Square, color: Color, dims: &Dimensions, friendly: BitBoard) -> BitBoard {
+    pub fn pawn_attacks<B: BB>(sq: Square, color: Color, dims: &Dimensions, friendly: B) -> B {
         let (file, rank) = sq.file_rank(dims);
-        let mut attacks = BitBoard::empty();
+        let mut attacks = B::empty_for_dims(dims);
         
         let forward = match color {
             Color::White => 1,
@@ -82,9 +82,9 @@ impl StandardPatterns {
         attacks
     }
     
-    pub fn pawn_pushes(sq: Square, color: Color, dims: &Dimensions, occupied: BitBoard) -> BitBoard {
+    pub fn pawn_pushes<B: BB>(sq: Square, color: Color, dims: &Dimensions, occupied: B) -> B {
         let (file, rank) = sq.file_rank(dims);
-        let mut pushes = BitBoard::empty();
+        let mut pushes = B::empty_for_dims(dims);
         
         let forward = match color {
             Color::White => 1,
@@ -1,48 +1,182 @@
-use crate::board::{Dimensions, Square};
+use crate::board::{BitBoard, Dimensions, Square, BB};
+use crate::movegen::MoveGenerator;
+use crate::notation::{san, uci, NotationError};
+use crate::piece::{PieceKind, PieceRegistry};
+use crate::position::Position;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum MoveType {
     Quiet = 0,
     Capture = 1,
     Promotion = 2,
     EnPassant = 3,
     Castling = 4,
+    /// A piece from the pocket placed on an empty square
+    Drop = 5,
+    /// A piece moved to an empty square regardless of its pattern, as through a wormhole
+    Teleport = 6,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Move(u32);
+/// A move packed into 64 bits:
+///
+/// | bits  | field                                                  |
+/// |-------|--------------------------------------------------------|
+/// | 0-7   | source square                                          |
+/// | 8-15  | target square                                          |
+/// | 16-18 | `MoveType`                                             |
+/// | 19-27 | promoted-to or dropped kind, as `PieceKind::index + 1` |
+/// | 28-36 | captured kind, as `PieceKind::index + 1`               |
+/// | 37-45 | square the duck lands on, plus one                     |
+/// | 46-54 | square a gated piece is placed on, plus one            |
+/// | 55-63 | gated kind, as `PieceKind::index + 1`                  |
+///
+/// A zero field means "none", so the primary move stays a plain `u64` comparison
+/// whatever variant actions are attached to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move(u64);
+
+/// Bit offset and width of each packed field
+type Field = (u32, u32);
+
+const SRC: Field = (0, 8);
+const DST: Field = (8, 8);
+const TYPE: Field = (16, 3);
+const PIECE: Field = (19, 9);
+const CAPTURED: Field = (28, 9);
+const DUCK: Field = (37, 9);
+const GATE_SQUARE: Field = (46, 9);
+const GATE_KIND: Field = (55, 9);
+
+#[inline]
+fn mask((shift, width): Field) -> u64 {
+    ((1 << width) - 1) << shift
+}
+
+/// Kinds are stored one above their index so that zero means none; nine bits hold
+/// every index up to `Custom(255)`
+fn encode_kind(kind: PieceKind) -> u64 {
+    kind.index() as u64 + 1
+}
 
 impl Move {
-    pub fn new(src: Square, dst: Square, move_type: MoveType, flags: u8) -> Self {
-        let v = (src.0 as u32)
-            | ((dst.0 as u32) << 8)
-            | ((move_type as u32) << 16)
-            | ((flags as u32) << 24);
-        Self(v)
+    pub fn new(src: Square, dst: Square, move_type: MoveType) -> Self {
+        debug_assert!(src.0 <= u8::MAX as u16 && dst.0 <= u8::MAX as u16);
+        Self(0)
+            .with_field(SRC, src.0 as u64)
+            .with_field(DST, dst.0 as u64)
+            .with_field(TYPE, move_type as u64)
+    }
+
+    pub fn new_promotion(src: Square, dst: Square, promo: PieceKind) -> Self {
+        Self::new(src, dst, MoveType::Promotion).with_field(PIECE, encode_kind(promo))
+    }
+
+    /// Drop of a `kind` piece from the pocket onto `dst`; the source square is set to `dst`
+    pub fn new_drop(kind: PieceKind, dst: Square) -> Self {
+        Self::new(dst, dst, MoveType::Drop).with_field(PIECE, encode_kind(kind))
+    }
+
+    /// Record the kind this move takes, for move ordering. Every capture made by
+    /// `MoveGenerator` and the notation parsers carries it.
+    pub fn with_captured(self, kind: PieceKind) -> Self {
+        self.with_field(CAPTURED, encode_kind(kind))
     }
 
+    /// After the move, place the duck on `sq`, an empty square other than the one it
+    /// stands on
+    pub fn with_duck(self, sq: Square) -> Self {
+        self.with_field(DUCK, sq.0 as u64 + 1)
+    }
+
+    /// After the move, take a `kind` piece from the mover's pocket and place it on
+    /// `sq`, usually the square the moving piece left, as in Seirawan chess
+    pub fn with_gating(self, kind: PieceKind, sq: Square) -> Self {
+        self.with_field(GATE_SQUARE, sq.0 as u64 + 1).with_field(GATE_KIND, encode_kind(kind))
+    }
+
+    #[inline]
+    fn with_field(self, field: Field, value: u64) -> Self {
+        debug_assert!(value << field.0 & !mask(field) == 0, "{value} does not fit a move field");
+        Self(self.0 & !mask(field) | value << field.0)
+    }
+
+    #[inline]
+    fn field(self, field: Field) -> u16 {
+        ((self.0 & mask(field)) >> field.0) as u16
+    }
+
+    fn kind_field(self, field: Field) -> Option<PieceKind> {
+        match self.field(field) {
+            0 => None,
+            value => Some(PieceKind::from_index(value as usize - 1)),
+        }
+    }
+
+    fn square_field(self, field: Field) -> Option<Square> {
+        match self.field(field) {
+            0 => None,
+            value => Some(Square(value - 1)),
+        }
+    }
+
+    #[inline]
     pub fn src(self) -> Square {
-        Square((self.0 & 0xFF) as u16)
+        Square(self.field(SRC))
     }
 
+    #[inline]
     pub fn dst(self) -> Square {
-        Square(((self.0 >> 8) & 0xFF) as u16)
+        Square(self.field(DST))
     }
 
     pub fn kind(self) -> MoveType {
-        match ((self.0 >> 16) & 0xFF) as u8 {
+        match self.field(TYPE) {
             0 => MoveType::Quiet,
             1 => MoveType::Capture,
             2 => MoveType::Promotion,
             3 => MoveType::EnPassant,
             4 => MoveType::Castling,
+            5 => MoveType::Drop,
+            6 => MoveType::Teleport,
             _ => unreachable!(),
         }
     }
 
-    pub fn flags(self) -> u8 {
-        ((self.0 >> 24) & 0xFF) as u8
+    /// Piece a promotion turns into
+    pub fn promotion_kind(self) -> Option<PieceKind> {
+        if self.kind() != MoveType::Promotion {
+            return None;
+        }
+        self.kind_field(PIECE)
+    }
+
+    /// Piece a drop places on the board
+    pub fn drop_kind(self) -> Option<PieceKind> {
+        if self.kind() != MoveType::Drop {
+            return None;
+        }
+        self.kind_field(PIECE)
+    }
+
+    /// Kind taken by this move, if it was recorded with `with_captured`
+    pub fn captured(self) -> Option<PieceKind> {
+        self.kind_field(CAPTURED)
+    }
+
+    pub fn duck_square(self) -> Option<Square> {
+        self.square_field(DUCK)
+    }
+
+    /// Gated kind and the square it is placed on
+    pub fn gating(self) -> Option<(PieceKind, Square)> {
+        Some((self.kind_field(GATE_KIND)?, self.square_field(GATE_SQUARE)?))
+    }
+
+    /// The move without its duck placement or gating
+    pub fn primary(self) -> Self {
+        Self(self.0 & !(mask(DUCK) | mask(GATE_SQUARE) | mask(GATE_KIND)))
     }
 
     pub fn debug_string(self, dims: &Dimensions) -> String {
@@ -54,17 +188,42 @@ impl Move {
         )
     }
 
+    /// Long algebraic notation with the standard piece letters; `uci::format_move`
+    /// takes the registry for custom promotion pieces
     pub fn to_uci(self, dims: &Dimensions) -> String {
-        let mut s = format!(
-            "{}{}",
-            self.src().to_string(dims),
-            self.dst().to_string(dims)
-        );
+        uci::format_move(self, dims, &PieceRegistry::<BitBoard>::standard())
+    }
 
-        if self.kind() == MoveType::Promotion {
-            s.push('q');
-        }
+    /// Parse long algebraic notation such as `e2e4` or `a10a11q` in `pos` with the
+    /// standard piece letters
+    pub fn from_uci<B: BB>(text: &str, pos: &Position<B>) -> Result<Self, NotationError> {
+        uci::parse_move(text, pos, &PieceRegistry::standard())
+    }
+
+    /// Standard algebraic notation for this move, which must be legal in `pos`
+    pub fn to_san<B: BB>(self, pos: &Position<B>, gen: &MoveGenerator<B>) -> String {
+        san::format_move(self, pos, gen)
+    }
+
+    /// Find the legal move in `pos` written in standard algebraic notation
+    pub fn from_san<B: BB>(text: &str, pos: &Position<B>, gen: &MoveGenerator<B>) -> Result<Self, NotationError> {
+        san::parse_move(text, pos, gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        s
+    #[test]
+    fn move_fields_hold_every_custom_kind() {
+        let (src, dst, gate) = (Square(0), Square(255), Square(1));
+        let last = PieceKind::Custom(u8::MAX);
+        let mv = Move::new_promotion(src, dst, last).with_captured(last).with_duck(Square(255)).with_gating(last, gate);
+        assert_eq!((mv.src(), mv.dst(), mv.kind()), (src, dst, MoveType::Promotion));
+        assert_eq!((mv.promotion_kind(), mv.captured()), (Some(last), Some(last)));
+        assert_eq!((mv.duck_square(), mv.gating()), (Some(Square(255)), Some((last, gate))));
+        assert_eq!(Move::new_drop(last, dst).drop_kind(), Some(last));
+        assert_eq!(Move::new(src, dst, MoveType::Teleport).kind(), MoveType::Teleport);
     }
 }
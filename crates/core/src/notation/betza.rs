@@ -0,0 +1,277 @@
+//! Betza piece notation, the subset that `DivergentPattern` can express.
+//!
+//! A definition is a run of atoms, each with optional lowercase modifiers in front
+//! and an optional range behind:
+//!
+//! - Atoms: `W` `F` `D` `N` `A` `H` `C` `Z` `G` are the leaps (0,1), (1,1), (0,2),
+//!   (1,2), (2,2), (0,3), (1,3), (2,3) and (3,3) in all their directions; `R`, `B`
+//!   and `Q` are the rook, bishop and queen riders, `K` the king.
+//! - Range: a doubled leap rides (`NN` is the nightrider), a number bounds the
+//!   leaps per move (`W3`, `R4`), and `0` means unlimited.
+//! - Directions: `f` `b` `l` `r` keep the leaps forward, backward, left or right,
+//!   `v` and `s` the vertical and sideways ones; `f` or `b` right before one of
+//!   `l` `r` `v` `s` means both at once, so `flF` is the single forward-left step
+//!   and `fsN` the two wide forward knight leaps. Forward is seen from the owner.
+//! - Modes: `m` moves without capturing, `c` captures only.
+//!
+//! Other modifiers, such as lame (`n`), hopping (`p`, `g`) or initial (`i`) moves,
+//! are reported as unsupported.
+
+use std::fmt;
+
+use crate::movegen::patterns::{Direction, DivergentPattern, Leg, LegMode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BetzaError {
+    Empty,
+    UnknownAtom(char),
+    UnsupportedModifier(char),
+    /// Modifiers at the end of the text with no atom to apply to
+    MissingAtom,
+    /// The direction modifiers rule out every leap of the atom, as in `flW`
+    NoDirections(String),
+    /// A range over 255 leaps
+    InvalidRange(String),
+    UnexpectedChar(char),
+}
+
+impl fmt::Display for BetzaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BetzaError::Empty => write!(f, "empty Betza definition"),
+            BetzaError::UnknownAtom(atom) => write!(f, "unknown Betza atom '{atom}'"),
+            BetzaError::UnsupportedModifier(modifier) => write!(f, "unsupported Betza modifier '{modifier}'"),
+            BetzaError::MissingAtom => write!(f, "Betza modifiers without an atom"),
+            BetzaError::NoDirections(part) => write!(f, "'{part}' leaves no direction to move in"),
+            BetzaError::InvalidRange(range) => write!(f, "invalid Betza range '{range}'"),
+            BetzaError::UnexpectedChar(ch) => write!(f, "unexpected '{ch}' in Betza definition"),
+        }
+    }
+}
+
+impl std::error::Error for BetzaError {}
+
+/// Build the pattern a Betza definition describes, e.g. `WfF`, `NN` or `fmWfcF`
+pub fn parse_pattern(text: &str) -> Result<DivergentPattern, BetzaError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(BetzaError::Empty);
+    }
+
+    let mut legs = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, _)) = chars.peek() {
+        let mut modifiers = String::new();
+        while let Some(&(_, ch)) = chars.peek() {
+            if !ch.is_ascii_lowercase() {
+                break;
+            }
+            modifiers.push(ch);
+            chars.next();
+        }
+
+        let (_, atom) = chars.next().ok_or(BetzaError::MissingAtom)?;
+        if !atom.is_ascii_uppercase() {
+            return Err(BetzaError::UnexpectedChar(atom));
+        }
+        let (leaps, mut max_distance) = atom_leaps(atom)?;
+        if chars.next_if(|&(_, ch)| ch == atom).is_some() {
+            max_distance = None;
+        }
+
+        let mut range = String::new();
+        while let Some((_, digit)) = chars.next_if(|(_, ch)| ch.is_ascii_digit()) {
+            range.push(digit);
+        }
+        if !range.is_empty() {
+            max_distance = match range.parse::<u8>() {
+                Ok(0) => None,
+                Ok(distance) => Some(distance),
+                Err(_) => return Err(BetzaError::InvalidRange(range)),
+            };
+        }
+
+        let (directions, mode) = parse_modifiers(&modifiers)?;
+        let before = legs.len();
+        for leap in leaps {
+            let kept = directions.is_empty()
+                || directions.iter().any(|&(first, second)| {
+                    direction_filter(first)(leap) && second.is_none_or(|second| direction_filter(second)(leap))
+                });
+            if kept {
+                legs.push(match max_distance {
+                    Some(distance) => Leg::step(leap, mode).with_max_distance(distance),
+                    None => Leg::slide(leap, mode),
+                });
+            }
+        }
+        if legs.len() == before {
+            let end = chars.peek().map_or(text.len(), |&(idx, _)| idx);
+            return Err(BetzaError::NoDirections(text[start..end].to_string()));
+        }
+    }
+
+    Ok(DivergentPattern::new(legs))
+}
+
+/// A direction modifier, or two that must both hold like `fl`
+type DirectionModifier = (char, Option<char>);
+
+/// Direction modifiers, any of which keeps a leap, and the move mode
+fn parse_modifiers(modifiers: &str) -> Result<(Vec<DirectionModifier>, LegMode), BetzaError> {
+    let mut directions = Vec::new();
+    let (mut moves, mut captures) = (false, false);
+
+    let mut chars = modifiers.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            'm' => moves = true,
+            'c' => captures = true,
+            'f' | 'b' => directions.push((ch, chars.next_if(|next| matches!(next, 'l' | 'r' | 'v' | 's')))),
+            'l' | 'r' | 'v' | 's' => directions.push((ch, None)),
+            _ => return Err(BetzaError::UnsupportedModifier(ch)),
+        }
+    }
+
+    let mode = match (moves, captures) {
+        (true, false) => LegMode::Move,
+        (false, true) => LegMode::Capture,
+        _ => LegMode::Both,
+    };
+    Ok((directions, mode))
+}
+
+fn direction_filter(modifier: char) -> fn(Direction) -> bool {
+    match modifier {
+        'f' => |leap| leap.rank_delta > 0,
+        'b' => |leap| leap.rank_delta < 0,
+        'l' => |leap| leap.file_delta < 0,
+        'r' => |leap| leap.file_delta > 0,
+        'v' => |leap| leap.rank_delta.abs() > leap.file_delta.abs(),
+        _ => |leap| leap.file_delta.abs() > leap.rank_delta.abs(),
+    }
+}
+
+/// Every leap of `atom` and the leaps per move it makes by default
+fn atom_leaps(atom: char) -> Result<(Vec<Direction>, Option<u8>), BetzaError> {
+    let (shapes, max_distance): (&[(i8, i8)], _) = match atom {
+        'W' => (&[(0, 1)], Some(1)),
+        'F' => (&[(1, 1)], Some(1)),
+        'D' => (&[(0, 2)], Some(1)),
+        'N' => (&[(1, 2)], Some(1)),
+        'A' => (&[(2, 2)], Some(1)),
+        'H' => (&[(0, 3)], Some(1)),
+        'C' => (&[(1, 3)], Some(1)),
+        'Z' => (&[(2, 3)], Some(1)),
+        'G' => (&[(3, 3)], Some(1)),
+        'K' => (&[(0, 1), (1, 1)], Some(1)),
+        'R' => (&[(0, 1)], None),
+        'B' => (&[(1, 1)], None),
+        'Q' => (&[(0, 1), (1, 1)], None),
+        _ => return Err(BetzaError::UnknownAtom(atom)),
+    };
+
+    let mut leaps = Vec::new();
+    for &(a, b) in shapes {
+        for (file_delta, rank_delta) in [(a, b), (b, a)] {
+            for (file_sign, rank_sign) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let leap = Direction { file_delta: file_delta * file_sign, rank_delta: rank_delta * rank_sign };
+                if !leaps.contains(&leap) {
+                    leaps.push(leap);
+                }
+            }
+        }
+    }
+    Ok((leaps, max_distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, Dimensions, Square, BB};
+    use crate::movegen::patterns::{JumpingPattern, MovePattern, RiderPattern, SlidingPattern};
+    use crate::piece::Color;
+
+    /// Compare the quiet moves and attacks of two patterns on every square of a
+    /// crowded board, for both colors
+    fn assert_same_moves(betza: &str, expected: &dyn MovePattern<BitBoard256>) {
+        let pattern = parse_pattern(betza).unwrap();
+        let dims = Dimensions::new(10, 10);
+        let empty = BitBoard256::empty_for_dims(&dims);
+        let occupied = (0..dims.num_squares()).step_by(7).fold(empty, |bb, idx| bb.set(Square(idx)));
+        let friendly = (0..dims.num_squares()).step_by(14).fold(empty, |bb, idx| bb.set(Square(idx)));
+        for idx in 0..dims.num_squares() {
+            for color in [Color::White, Color::Black] {
+                let sq = Square(idx);
+                let quiets = |pattern: &dyn MovePattern<BitBoard256>| {
+                    pattern
+                        .quiet_moves_from(sq, color, &dims, occupied, friendly)
+                        .unwrap_or_else(|| pattern.attacks_from(sq, color, &dims, occupied, friendly))
+                        .difference(occupied)
+                };
+                assert_eq!(
+                    pattern.attacks_from(sq, color, &dims, occupied, friendly),
+                    expected.attacks_from(sq, color, &dims, occupied, friendly),
+                    "{betza} attacks from {idx}",
+                );
+                assert_eq!(quiets(&pattern), quiets(expected), "{betza} moves from {idx}");
+            }
+        }
+    }
+
+    #[test]
+    fn betza_atoms_match_the_built_in_patterns() {
+        assert_same_moves("K", &JumpingPattern::new(Direction::QUEEN_DIRS.to_vec()));
+        assert_same_moves("WF", &JumpingPattern::new(Direction::QUEEN_DIRS.to_vec()));
+        assert_same_moves("Q", &SlidingPattern::new(Direction::QUEEN_DIRS.to_vec()));
+        assert_same_moves("RB", &SlidingPattern::new(Direction::QUEEN_DIRS.to_vec()));
+        assert_same_moves("WW", &SlidingPattern::new(Direction::ROOK_DIRS.to_vec()));
+        assert_same_moves("R4", &SlidingPattern::with_max_distance(Direction::ROOK_DIRS.to_vec(), 4));
+        assert_same_moves("B0", &SlidingPattern::new(Direction::BISHOP_DIRS.to_vec()));
+        assert_same_moves("N", &JumpingPattern::new(Direction::KNIGHT_LEAPS.to_vec()));
+        assert_same_moves("NN", &RiderPattern::nightrider());
+        assert_same_moves("N3", &RiderPattern::with_max_distance(Direction::KNIGHT_LEAPS.to_vec(), 3));
+        assert_same_moves("fmWfcF", &DivergentPattern::new(vec![
+            Leg::step(Direction::NORTH, LegMode::Move),
+            Leg::step(Direction::NORTHWEST, LegMode::Capture),
+            Leg::step(Direction::NORTHEAST, LegMode::Capture),
+        ]));
+        assert_same_moves("fR", &DivergentPattern::new(vec![Leg::slide(Direction::NORTH, LegMode::Both)]));
+    }
+
+    #[test]
+    fn betza_direction_modifiers() {
+        let dims = Dimensions::standard();
+        let sq = |text: &str| Square::parse(text, &dims).unwrap();
+        let empty = BitBoard256::empty_for_dims(&dims);
+        let targets = |betza: &str, color| {
+            let mut bb = parse_pattern(betza).unwrap().attacks_from(sq("d4"), color, &dims, empty, empty);
+            let mut names = Vec::new();
+            while let Some(target) = bb.pop_lsb() {
+                names.push(target.to_string(&dims));
+            }
+            names.sort();
+            names
+        };
+
+        assert_eq!(targets("WfF", Color::White), ["c4", "c5", "d3", "d5", "e4", "e5"]);
+        assert_eq!(targets("WfF", Color::Black), ["c3", "c4", "d3", "d5", "e3", "e4"]);
+        assert_eq!(targets("flF", Color::White), ["c5"]);
+        assert_eq!(targets("flFbrF", Color::White), ["c5", "e3"]);
+        assert_eq!(targets("sW", Color::White), ["c4", "e4"]);
+        assert_eq!(targets("fvN", Color::White), ["c6", "e6"]);
+        assert_eq!(targets("fsN", Color::White), ["b5", "f5"]);
+    }
+
+    #[test]
+    fn betza_errors() {
+        assert_eq!(parse_pattern("").err(), Some(BetzaError::Empty));
+        assert_eq!(parse_pattern("X").err(), Some(BetzaError::UnknownAtom('X')));
+        assert_eq!(parse_pattern("nN").err(), Some(BetzaError::UnsupportedModifier('n')));
+        assert_eq!(parse_pattern("mRcpR").err(), Some(BetzaError::UnsupportedModifier('p')));
+        assert_eq!(parse_pattern("Wf").err(), Some(BetzaError::MissingAtom));
+        assert_eq!(parse_pattern("WflW").err(), Some(BetzaError::NoDirections("flW".to_string())));
+        assert_eq!(parse_pattern("R300").err(), Some(BetzaError::InvalidRange("300".to_string())));
+        assert_eq!(parse_pattern("W F").err(), Some(BetzaError::UnexpectedChar(' ')));
+    }
+}
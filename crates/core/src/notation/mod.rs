@@ -0,0 +1,118 @@
+//! Text formats for squares, pieces and moves on boards up to 16x16.
+//!
+//! Files are the letters `a` to `p`, ranks are written in decimal starting at 1,
+//! so squares range from `a1` to `p16`. Pieces are written with their symbols from
+//! a `PieceRegistry`.
+
+pub mod uci;
+pub mod san;
+pub mod pgn;
+pub mod betza;
+
+use std::fmt;
+
+use crate::{
+    board::{Dimensions, Square},
+    error::MoveError,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    InvalidSquare(String),
+    /// Malformed move text
+    InvalidMove(String),
+    UnknownPieceSymbol(String),
+    /// No legal move matches the text
+    IllegalMove(String),
+    /// More than one legal move matches the text
+    AmbiguousMove(String),
+    /// The move text is well formed but does not fit the position
+    Move(MoveError),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::InvalidSquare(text) => write!(f, "invalid square '{text}'"),
+            NotationError::InvalidMove(text) => write!(f, "invalid move '{text}'"),
+            NotationError::UnknownPieceSymbol(symbol) => write!(f, "unknown piece symbol '{symbol}'"),
+            NotationError::IllegalMove(text) => write!(f, "no legal move matches '{text}'"),
+            NotationError::AmbiguousMove(text) => write!(f, "move '{text}' is ambiguous"),
+            NotationError::Move(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<MoveError> for NotationError {
+    fn from(err: MoveError) -> Self {
+        NotationError::Move(err)
+    }
+}
+
+pub fn format_square(sq: Square, dims: &Dimensions) -> String {
+    let (file, rank) = sq.file_rank(dims);
+    format!("{}{}", (b'a' + file) as char, rank as u16 + 1)
+}
+
+pub fn parse_square(text: &str, dims: &Dimensions) -> Result<Square, NotationError> {
+    match split_square(text, dims) {
+        Some((sq, "")) => Ok(sq),
+        _ => Err(NotationError::InvalidSquare(text.to_string())),
+    }
+}
+
+/// Parse the square at the start of `text` and return it with the rest of the input.
+/// Ranks are read greedily, so `a10` is the tenth rank and never `a1` followed by `0`.
+pub(crate) fn split_square<'a>(text: &'a str, dims: &Dimensions) -> Option<(Square, &'a str)> {
+    let file_char = text.chars().next()?;
+    if !file_char.is_ascii_lowercase() {
+        return None;
+    }
+    let file = file_char as u8 - b'a';
+
+    let rest = &text[1..];
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || rest.starts_with('0') {
+        return None;
+    }
+    let rank: u8 = rest[..digits].parse().ok()?;
+
+    if file >= dims.width || rank > dims.height {
+        return None;
+    }
+    Some((Square::from_rank_file(rank - 1, file, dims), &rest[digits..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_round_trip_on_all_sizes() {
+        for (width, height) in [(5, 5), (8, 8), (10, 10), (16, 16), (7, 12)] {
+            let dims = Dimensions::new(width, height);
+            for idx in 0..dims.num_squares() {
+                let text = format_square(Square(idx), &dims);
+                assert_eq!(parse_square(&text, &dims), Ok(Square(idx)), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn square_text_uses_file_then_rank() {
+        let dims = Dimensions::new(16, 16);
+        assert_eq!(format_square(Square::from_rank_file(9, 0, &dims), &dims), "a10");
+        assert_eq!(format_square(Square::from_rank_file(15, 15, &dims), &dims), "p16");
+        assert_eq!(Square::from_rank_file(0, 4, &Dimensions::standard()).to_string(&Dimensions::standard()), "e1");
+    }
+
+    #[test]
+    fn parse_square_rejects_bad_input() {
+        let dims = Dimensions::standard();
+        for text in ["", "e", "e0", "e01", "e9", "i1", "E1", "e1x", "1e"] {
+            assert_eq!(parse_square(text, &dims), Err(NotationError::InvalidSquare(text.to_string())));
+        }
+    }
+}
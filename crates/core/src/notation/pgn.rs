@@ -0,0 +1,564 @@
+use std::fmt;
+
+use crate::{
+    board::{Dimensions, BB},
+    error::DimensionsError,
+    movegen::MoveGenerator,
+    moves::Move,
+    notation::{uci, NotationError},
+    piece::{Color, PieceRegistry},
+    position::{Fen, FenError, Position},
+};
+
+const STANDARD_START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Tags written first, in this order, when present
+const ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Tags that `PgnGame` keeps in dedicated fields
+const BOARD_SIZE_TAG: &str = "BoardSize";
+const FEN_TAG: &str = "FEN";
+const SETUP_TAG: &str = "SetUp";
+const PIECES_TAG: &str = "Pieces";
+
+const LINE_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// Malformed tag pair on the given line (1-based)
+    InvalidTag { line: usize },
+    InvalidBoardSize(String),
+    InvalidPieces(String),
+    /// A `Pieces` symbol missing from the generator's registry
+    UnknownPiece(String),
+    /// A registered piece with a pattern but no Betza text to record it by
+    UndefinedPiece(String),
+    /// A `Pieces` definition that differs from the registry's, which is `None` when
+    /// the registered piece has no Betza text
+    PieceMismatch { symbol: String, recorded: String, registered: Option<String> },
+    Dimensions(DimensionsError),
+    /// A non-standard board without a `FEN` tag
+    MissingStartPosition,
+    Fen(FenError),
+    /// Move `number` of `side` could not be read or played
+    Move { number: u16, side: Color, san: String, error: NotationError },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::InvalidTag { line } => write!(f, "malformed PGN tag on line {line}"),
+            PgnError::InvalidBoardSize(value) => write!(f, "invalid board size '{value}'"),
+            PgnError::InvalidPieces(value) => write!(f, "invalid piece definitions '{value}'"),
+            PgnError::UnknownPiece(symbol) => write!(f, "piece '{symbol}' is not registered"),
+            PgnError::UndefinedPiece(symbol) => write!(f, "piece '{symbol}' has no Betza definition to record"),
+            PgnError::PieceMismatch { symbol, recorded, registered } => match registered {
+                Some(registered) => {
+                    write!(f, "piece '{symbol}' is recorded as '{recorded}' but registered as '{registered}'")
+                }
+                None => write!(f, "piece '{symbol}' is recorded as '{recorded}' but registered without a definition"),
+            },
+            PgnError::Dimensions(err) => err.fmt(f),
+            PgnError::MissingStartPosition => write!(f, "non-standard board without a FEN tag"),
+            PgnError::Fen(err) => err.fmt(f),
+            PgnError::Move { number, side, san, error } => {
+                let dots = if *side == Color::White { "." } else { "..." };
+                write!(f, "move {number}{dots} {san}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+    fn from(err: FenError) -> Self {
+        PgnError::Fen(err)
+    }
+}
+
+impl From<DimensionsError> for PgnError {
+    fn from(err: DimensionsError) -> Self {
+        PgnError::Dimensions(err)
+    }
+}
+
+/// One game record. Variant metadata lives in its own fields and is written as the
+/// `BoardSize` (`WIDTHxHEIGHT`), `SetUp`/`FEN` and `Pieces` (`X:definition;Y:definition`)
+/// tags; every other tag is kept in `tags` in file order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub dims: Dimensions,
+    /// Starting position, `None` for the standard 8x8 setup
+    pub start_fen: Option<String>,
+    /// Custom piece symbols with their move definitions
+    pub pieces: Vec<(String, String)>,
+    /// Moves in standard algebraic notation
+    pub moves: Vec<String>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn new(dims: Dimensions, start_fen: Option<String>) -> Self {
+        Self {
+            tags: Vec::new(),
+            dims,
+            start_fen,
+            pieces: Vec::new(),
+            moves: Vec::new(),
+            result: "*".to_string(),
+        }
+    }
+
+    /// Record `moves` played from `start`. Every registered piece with a pattern and a
+    /// symbol goes into `pieces` by its Betza text.
+    pub fn from_moves<B: BB>(
+        start: &Position<B>,
+        moves: &[Move],
+        gen: &MoveGenerator<B>,
+    ) -> Result<Self, PgnError> {
+        let fen = Fen::to_string_with(start, gen.registry())?;
+        let standard = start.dims == Dimensions::standard() && fen == STANDARD_START;
+        let mut game = Self::new(start.dims, (!standard).then_some(fen));
+        for def in gen.registry().iter().filter(|def| def.pattern.is_some()) {
+            let symbol = def.symbol(Color::White);
+            if symbol.is_empty() {
+                continue;
+            }
+            let betza = def.betza.clone().ok_or_else(|| PgnError::UndefinedPiece(symbol.to_string()))?;
+            game.pieces.push((symbol.to_string(), betza));
+        }
+
+        let mut pos = start.clone();
+        for &mv in moves {
+            // SAN is only defined for legal moves; a move without its captured kind
+            // still matches the generated one
+            let legal = gen.generate_legal(&pos).into_iter().any(|legal| {
+                legal == mv || mv.captured().is_none() && legal.captured().is_some_and(|kind| mv.with_captured(kind) == legal)
+            });
+            if !legal {
+                let text = uci::format_move(mv, &pos.dims, gen.registry());
+                return Err(move_error(&pos, &text, NotationError::IllegalMove(text.clone())));
+            }
+            let san = mv.to_san(&pos, gen);
+            pos.try_make_move(mv).map_err(|err| move_error(&pos, &san, err.into()))?;
+            game.moves.push(san);
+        }
+        Ok(game)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn start_position<B: BB>(&self, registry: &PieceRegistry<B>) -> Result<Position<B>, PgnError> {
+        match &self.start_fen {
+            Some(fen) => Ok(Fen::parse_with(fen, self.dims, registry)?),
+            None if self.dims == Dimensions::standard() => Ok(Fen::parse_with(STANDARD_START, self.dims, registry)?),
+            None => Err(PgnError::MissingStartPosition),
+        }
+    }
+
+    /// Play the recorded moves from the start position. The generator's registry
+    /// has to define every piece in `pieces` with the same Betza text.
+    pub fn replay<B: BB>(&self, gen: &MoveGenerator<B>) -> Result<(Position<B>, Vec<Move>), PgnError> {
+        let registry = gen.registry();
+        for (symbol, recorded) in &self.pieces {
+            let piece = registry.piece_for_symbol(symbol).ok_or_else(|| PgnError::UnknownPiece(symbol.clone()))?;
+            let registered = registry.get(piece.kind).and_then(|def| def.betza.clone());
+            if registered.as_deref() != Some(recorded.trim()) {
+                return Err(PgnError::PieceMismatch { symbol: symbol.clone(), recorded: recorded.clone(), registered });
+            }
+        }
+        let mut pos = self.start_position(gen.registry())?;
+        let mut played = Vec::with_capacity(self.moves.len());
+
+        for san in &self.moves {
+            let mv = Move::from_san(san, &pos, gen).map_err(|err| move_error(&pos, san, err))?;
+            pos.make_move(mv);
+            played.push(mv);
+        }
+        Ok((pos, played))
+    }
+}
+
+fn move_error<B: BB>(pos: &Position<B>, san: &str, error: NotationError) -> PgnError {
+    PgnError::Move {
+        number: pos.fullmove_number,
+        side: pos.side_to_move,
+        san: san.to_string(),
+        error,
+    }
+}
+
+pub struct Pgn;
+
+impl Pgn {
+    /// Parse a single game. Comments, variations and numeric annotation glyphs are skipped.
+    pub fn parse(input: &str) -> Result<PgnGame, PgnError> {
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+
+        for (line_idx, line) in input.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && movetext.trim().is_empty() {
+                tags.push(parse_tag(trimmed).ok_or(PgnError::InvalidTag { line: line_idx + 1 })?);
+            } else if !trimmed.starts_with('%') {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+
+        let mut game = PgnGame::new(Dimensions::standard(), None);
+        for (name, value) in tags {
+            match name.as_str() {
+                BOARD_SIZE_TAG => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .ok_or_else(|| PgnError::InvalidBoardSize(value.clone()))?;
+                    game.dims = Dimensions::try_new(width, height)?;
+                }
+                FEN_TAG => game.start_fen = Some(value),
+                SETUP_TAG => {}
+                PIECES_TAG => game.pieces = parse_pieces(&value)?,
+                _ => game.tags.push((name, value)),
+            }
+        }
+
+        let (moves, result) = parse_movetext(&movetext);
+        game.moves = moves;
+        game.result = result
+            .or_else(|| game.tag("Result").map(str::to_string))
+            .unwrap_or_else(|| "*".to_string());
+        Ok(game)
+    }
+
+    pub fn to_string(game: &PgnGame) -> String {
+        let mut out = String::new();
+        let mut write_tag = |name: &str, value: &str| {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("[{name} \"{escaped}\"]\n"));
+        };
+
+        for name in ROSTER {
+            match (name, game.tag(name)) {
+                ("Result", _) => write_tag(name, &game.result),
+                (_, Some(value)) => write_tag(name, value),
+                (_, None) => write_tag(name, "?"),
+            }
+        }
+        for (name, value) in &game.tags {
+            if !ROSTER.contains(&name.as_str()) {
+                write_tag(name, value);
+            }
+        }
+        if game.dims != Dimensions::standard() {
+            write_tag(BOARD_SIZE_TAG, &format!("{}x{}", game.dims.width, game.dims.height));
+        }
+        if let Some(fen) = &game.start_fen {
+            write_tag(SETUP_TAG, "1");
+            write_tag(FEN_TAG, fen);
+        }
+        if !game.pieces.is_empty() {
+            let pieces: Vec<String> = game.pieces.iter().map(|(symbol, def)| format!("{symbol}:{def}")).collect();
+            write_tag(PIECES_TAG, &pieces.join(";"));
+        }
+        out.push('\n');
+
+        // numbering follows the start position, which may have black to move
+        let (mut number, mut side) = game
+            .start_fen
+            .as_deref()
+            .map(start_number_and_side)
+            .unwrap_or((1, Color::White));
+
+        let mut tokens = Vec::with_capacity(game.moves.len() * 3 / 2 + 1);
+        for (idx, san) in game.moves.iter().enumerate() {
+            match side {
+                Color::White => tokens.push(format!("{number}.")),
+                Color::Black if idx == 0 => tokens.push(format!("{number}...")),
+                Color::Black => {}
+            }
+            tokens.push(san.clone());
+            if side == Color::Black {
+                number += 1;
+            }
+            side = side.opposite();
+        }
+        tokens.push(game.result.clone());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > LINE_WIDTH {
+                out.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                out.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            out.push_str(&token);
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// `[Name "value"]` with `\"` and `\\` escapes
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, rest) = inner.split_once(char::is_whitespace)?;
+    let quoted = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => value.push(chars.next()?),
+            '"' => return None,
+            _ => value.push(ch),
+        }
+    }
+    Some((name.to_string(), value))
+}
+
+fn parse_pieces(value: &str) -> Result<Vec<(String, String)>, PgnError> {
+    let invalid = || PgnError::InvalidPieces(value.to_string());
+    value
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (symbol, def) = entry.trim().split_once(':').ok_or_else(invalid)?;
+            if symbol.is_empty() || symbol.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            Ok((symbol.to_string(), def.to_string()))
+        })
+        .collect()
+}
+
+/// SAN tokens and the game termination marker, if any
+fn parse_movetext(text: &str) -> (Vec<String>, Option<String>) {
+    let mut moves = Vec::new();
+    let mut result = None;
+    let mut depth = 0usize; // variation nesting
+    let mut chars = text.chars();
+    let mut token = String::new();
+
+    let mut flush = |token: &mut String, depth: usize| {
+        if depth == 0 && !token.is_empty() {
+            match token.as_str() {
+                "1-0" | "0-1" | "1/2-1/2" | "*" => result = Some(token.clone()),
+                _ if token.starts_with('$') => {}
+                _ => {
+                    let san = strip_move_number(token);
+                    if !san.is_empty() {
+                        moves.push(san.to_string());
+                    }
+                }
+            }
+        }
+        token.clear();
+    };
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => {
+                flush(&mut token, depth);
+                for inner in chars.by_ref() {
+                    if inner == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                flush(&mut token, depth);
+                for inner in chars.by_ref() {
+                    if inner == '\n' {
+                        break;
+                    }
+                }
+            }
+            '(' => {
+                flush(&mut token, depth);
+                depth += 1;
+            }
+            ')' => {
+                flush(&mut token, depth);
+                depth = depth.saturating_sub(1);
+            }
+            _ if ch.is_whitespace() => flush(&mut token, depth),
+            _ => token.push(ch),
+        }
+    }
+    flush(&mut token, depth);
+    (moves, result)
+}
+
+/// Drop a leading move number such as `12.` or `12...`, which may be glued to the move
+fn strip_move_number(token: &str) -> &str {
+    let rest = token.trim_start_matches(|ch: char| ch.is_ascii_digit());
+    if rest.is_empty() {
+        rest
+    } else if rest.len() < token.len() && rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        token
+    }
+}
+
+/// Full move number and side to move from the last two FEN fields
+fn start_number_and_side(fen: &str) -> (u16, Color) {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let side = match fields.get(1) {
+        Some(&"b") | Some(&"B") => Color::Black,
+        _ => Color::White,
+    };
+    let number = fields.get(5).and_then(|n| n.parse().ok()).unwrap_or(1);
+    (number, side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64, Square};
+    use crate::moves::MoveType;
+    use crate::movegen::patterns::{Direction, JumpingPattern};
+    use crate::piece::{PieceDef, PieceKind};
+
+    #[test]
+    fn pgn_reads_standard_game_with_comments_and_variations() {
+        let text = r#"[Event "Casual \"blitz\""]
+[Site "?"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 {king pawn} e5 2. Qh5 (2. Nf3 Nc6 (2... d6) 3. Bb5) 2... Nc6 $1
+3.Bc4 Nf6?? ; Scholar's mate
+4. Qxf7# 1-0
+"#;
+        let game = Pgn::parse(text).unwrap();
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.moves, ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6??", "Qxf7#"]);
+        assert_eq!(game.result, "1-0");
+
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::new(game.dims);
+        let (pos, played) = game.replay(&gen).unwrap();
+        assert_eq!(played.len(), 7);
+        assert!(gen.in_check(&pos));
+        assert!(gen.generate_legal(&pos).is_empty());
+    }
+
+    #[test]
+    fn pgn_round_trips_variant_game() {
+        let dims = Dimensions::new(10, 10);
+        let fen = "rnbqkxbnqr/pppppppppp/10/10/10/10/10/10/PPPPPPPPPP/RNBQKXBNQR b - - 0 7";
+        let mut registry = PieceRegistry::standard();
+        registry.register(PieceDef::new(PieceKind::Custom(23), "jumper", "X").with_betza("vH").unwrap()).unwrap();
+        let start: Position<BitBoard256> = Fen::parse_with(fen, dims, &registry).unwrap();
+        let gen = MoveGenerator::with_registry(dims, registry);
+
+        let mut pos = start.clone();
+        let mut moves = Vec::new();
+        for uci in ["f10f7", "f1f4", "a9a7", "a2a4"] {
+            let mv = Move::from_uci(uci, &pos).unwrap();
+            pos.make_move(mv);
+            moves.push(mv);
+        }
+
+        let mut game = PgnGame::from_moves(&start, &moves, &gen).unwrap();
+        assert_eq!(game.pieces, [("X".to_string(), "vH".to_string())]);
+        game.set_tag("Variant", "Grand jumpers");
+        let text = Pgn::to_string(&game);
+        assert!(text.contains("[BoardSize \"10x10\"]"));
+        assert!(text.contains("[Pieces \"X:vH\"]"));
+        assert!(text.contains("7... Xf7 8. Xf4 a7 9. a4 *"));
+
+        let parsed = Pgn::parse(&text).unwrap();
+        assert_eq!(parsed.dims, dims);
+        assert_eq!(parsed.pieces, game.pieces);
+        assert_eq!(parsed.tag("Variant"), Some("Grand jumpers"));
+        let (end, replayed) = parsed.replay(&gen).unwrap();
+        assert_eq!(replayed, moves);
+        assert_eq!(Fen::to_string_with(&end, gen.registry()).unwrap(), Fen::to_string_with(&pos, gen.registry()).unwrap());
+
+        let standard: MoveGenerator<BitBoard256> = MoveGenerator::new(dims);
+        assert_eq!(parsed.replay(&standard).unwrap_err(), PgnError::UnknownPiece("X".to_string()));
+
+        let mut other = PieceRegistry::standard();
+        other.register(PieceDef::new(PieceKind::Custom(23), "jumper", "X").with_betza("N").unwrap()).unwrap();
+        let err = parsed.replay(&MoveGenerator::<BitBoard256>::with_registry(dims, other)).unwrap_err();
+        assert_eq!(
+            err,
+            PgnError::PieceMismatch { symbol: "X".to_string(), recorded: "vH".to_string(), registered: Some("N".to_string()) },
+        );
+        assert_eq!(err.to_string(), "piece 'X' is recorded as 'vH' but registered as 'N'");
+
+        let mut bare = PieceRegistry::standard();
+        let pattern = JumpingPattern::new(vec![
+            Direction { file_delta: 0, rank_delta: 3 },
+            Direction { file_delta: 0, rank_delta: -3 },
+        ]);
+        bare.register(PieceDef::new(PieceKind::Custom(23), "jumper", "X").with_pattern(Box::new(pattern))).unwrap();
+        let bare = MoveGenerator::<BitBoard256>::with_registry(dims, bare);
+        assert_eq!(PgnGame::from_moves(&start, &moves, &bare).unwrap_err(), PgnError::UndefinedPiece("X".to_string()));
+    }
+
+    #[test]
+    fn pgn_reports_offending_move() {
+        let game = Pgn::parse("1. e4 e5 2. Nf3 Ke7 3. Ke2 Nf3 *").unwrap();
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::new(game.dims);
+
+        let err = game.replay(&gen).unwrap_err();
+        assert_eq!(
+            err,
+            PgnError::Move {
+                number: 3,
+                side: Color::Black,
+                san: "Nf3".to_string(),
+                error: NotationError::IllegalMove("Nf3".to_string()),
+            },
+        );
+        assert_eq!(err.to_string(), "move 3... Nf3: no legal move matches 'Nf3'");
+
+        let start: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", Dimensions::standard()).unwrap();
+        let sq = |text| Square::parse(text, &start.dims).unwrap();
+        let empty_source = Move::new(sq("e3"), sq("e4"), MoveType::Quiet);
+        assert_eq!(
+            PgnGame::from_moves(&start, &[empty_source], &gen).unwrap_err(),
+            PgnError::Move {
+                number: 1,
+                side: Color::White,
+                san: "e3e4".to_string(),
+                error: NotationError::IllegalMove("e3e4".to_string()),
+            },
+        );
+        let start: Position<BitBoard64> = Fen::parse(STANDARD_START, Dimensions::standard()).unwrap();
+        let impossible = Move::new(sq("b1"), sq("b8"), MoveType::Quiet);
+        assert!(matches!(
+            PgnGame::from_moves(&start, &[impossible], &gen),
+            Err(PgnError::Move { error: NotationError::IllegalMove(_), .. }),
+        ));
+
+        assert_eq!(Pgn::parse("[Event \"x\"\n1. e4 *"), Err(PgnError::InvalidTag { line: 1 }));
+        assert_eq!(
+            Pgn::parse("[BoardSize \"10x\"]\n*"),
+            Err(PgnError::InvalidBoardSize("10x".to_string())),
+        );
+        assert_eq!(
+            Pgn::parse("[BoardSize \"10x10\"]\n1. e4 *").unwrap().start_position(&PieceRegistry::<BitBoard256>::standard()).unwrap_err(),
+            PgnError::MissingStartPosition,
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use crate::{
+    board::{Square, BB},
+    movegen::MoveGenerator,
+    moves::{Move, MoveType},
+    notation::{format_square, split_square, NotationError},
+    piece::{Color, Piece, PieceKind, PieceRegistry},
+    position::Position,
+};
+
+/// Standard algebraic notation for a legal move in `pos`, e.g. `Nbd7`, `exd6`,
+/// `a10=Q+`, `N@f7#` or `O-O-O#`. Pieces are written with their white symbol from the
+/// generator's registry.
+pub fn format_move<B: BB>(mv: Move, pos: &Position<B>, gen: &MoveGenerator<B>) -> String {
+    let dims = &pos.dims;
+    let registry = gen.registry();
+    let src = mv.src();
+    let dst = mv.dst();
+    let kind = pos.piece_at(src).map_or(PieceKind::Pawn, |piece| piece.kind);
+    let is_capture = matches!(mv.kind(), MoveType::Capture | MoveType::EnPassant) || pos.is_occupied(dst);
+
+    let mut text = String::new();
+    if let Some(drop) = mv.drop_kind() {
+        text.push_str(symbol(registry, drop));
+        text.push('@');
+        text.push_str(&format_square(dst, dims));
+    } else if mv.kind() == MoveType::Castling {
+        let (src_file, _) = src.file_rank(dims);
+        let (dst_file, _) = dst.file_rank(dims);
+        text.push_str(if dst_file > src_file { "O-O" } else { "O-O-O" });
+    } else if kind == PieceKind::Pawn {
+        if is_capture {
+            text.push((b'a' + src.file_rank(dims).0) as char);
+            text.push('x');
+        }
+        text.push_str(&format_square(dst, dims));
+        if let Some(promo) = mv.promotion_kind() {
+            text.push('=');
+            text.push_str(symbol(registry, promo));
+        }
+    } else {
+        text.push_str(symbol(registry, kind));
+        text.push_str(&disambiguation(mv, kind, pos, gen));
+        if is_capture {
+            text.push('x');
+        }
+        text.push_str(&format_square(dst, dims));
+    }
+
+    let mut after = pos.clone();
+    after.make_move(mv);
+    if gen.in_check(&after) {
+        text.push(if gen.generate_legal(&after).is_empty() { '#' } else { '+' });
+    }
+    text
+}
+
+/// Find the legal move in `pos` written as `text`. Check, mate and annotation
+/// suffixes are ignored, castling may be written with `O` or `0`, and promotions
+/// with or without `=`. Pawn drops may leave out the `P`. Piece symbols of either color are accepted; where they
+/// overlap the longest one wins, so a promotion symbol containing lower-case
+/// letters needs the `=`.
+pub fn parse_move<B: BB>(text: &str, pos: &Position<B>, gen: &MoveGenerator<B>) -> Result<Move, NotationError> {
+    let invalid = || NotationError::InvalidMove(text.to_string());
+    let dims = &pos.dims;
+    let body = text.trim_end_matches(['+', '#', '!', '?']);
+    let legal = gen.generate_legal(pos);
+
+    let castle_long = matches!(body, "O-O-O" | "0-0-0");
+    if castle_long || matches!(body, "O-O" | "0-0") {
+        return legal
+            .into_iter()
+            .find(|mv| {
+                let (src_file, _) = mv.src().file_rank(dims);
+                let (dst_file, _) = mv.dst().file_rank(dims);
+                mv.kind() == MoveType::Castling && (dst_file < src_file) == castle_long
+            })
+            .ok_or_else(|| NotationError::IllegalMove(text.to_string()));
+    }
+
+    // drops, `N@f3`, or `@e4` for a pawn
+    if let Some((symbol, target)) = body.split_once('@') {
+        let kind = match symbol {
+            "" => PieceKind::Pawn,
+            _ => gen.registry()
+                .piece_for_symbol(symbol)
+                .ok_or_else(|| NotationError::UnknownPieceSymbol(symbol.to_string()))?
+                .kind,
+        };
+        let dst = match split_square(target, dims) {
+            Some((sq, "")) => sq,
+            _ => return Err(invalid()),
+        };
+        return legal
+            .into_iter()
+            .find(|mv| mv.drop_kind() == Some(kind) && mv.dst() == dst)
+            .ok_or_else(|| NotationError::IllegalMove(text.to_string()));
+    }
+
+    // a leading symbol names the piece, pawn moves start with the file letter
+    let (kind, rest) = match body.chars().next() {
+        Some(ch) if ch.is_ascii_lowercase() => (PieceKind::Pawn, body),
+        Some(_) => {
+            let (piece, len) = gen.registry().match_symbol(body).ok_or_else(|| {
+                let end = body.find(|ch: char| ch.is_ascii_lowercase()).unwrap_or(body.len());
+                NotationError::UnknownPieceSymbol(body[..end].to_string())
+            })?;
+            (piece.kind, &body[len..])
+        }
+        None => return Err(invalid()),
+    };
+
+    // trailing promotion, `e8=Q` or `e8Q`
+    let (rest, promo_symbol) = match rest.split_once('=') {
+        Some((rest, symbol)) if !symbol.is_empty() => (rest, Some(symbol)),
+        Some(_) => return Err(invalid()),
+        None => (rest, None),
+    };
+
+    // the target is the last file letter with its rank; 'x' can only come before it
+    let dst_at = rest.rfind(|ch: char| ch.is_ascii_lowercase()).ok_or_else(invalid)?;
+    let (dst, promo_symbol) = match (split_square(&rest[dst_at..], dims), promo_symbol) {
+        (Some((sq, "")), promo_symbol) => (sq, promo_symbol),
+        (Some((sq, symbol)), None) => (sq, Some(symbol)),
+        _ => return Err(invalid()),
+    };
+    let promotion = match promo_symbol {
+        Some(symbol) => Some(
+            gen.registry()
+                .piece_for_symbol(symbol)
+                .ok_or_else(|| NotationError::UnknownPieceSymbol(symbol.to_string()))?
+                .kind,
+        ),
+        None => None,
+    };
+    let (from_file, from_rank) = parse_disambiguation(rest[..dst_at].trim_end_matches('x'), dims.height)
+        .ok_or_else(invalid)?;
+
+    let mut matching = legal.into_iter().filter(|mv| {
+        let (file, rank) = mv.src().file_rank(dims);
+        mv.dst() == dst
+            && mv.kind() != MoveType::Castling
+            && pos.piece_at(mv.src()).map(|piece| piece.kind) == Some(kind)
+            && mv.promotion_kind() == promotion
+            && from_file.is_none_or(|f| f == file)
+            && from_rank.is_none_or(|r| r == rank)
+    });
+    match (matching.next(), matching.next()) {
+        (Some(mv), None) => Ok(mv),
+        (Some(_), Some(_)) => Err(NotationError::AmbiguousMove(text.to_string())),
+        (None, _) => Err(NotationError::IllegalMove(text.to_string())),
+    }
+}
+
+fn symbol<B>(registry: &PieceRegistry<B>, kind: PieceKind) -> &str {
+    registry.symbol(Piece { color: Color::White, kind }).unwrap_or("?")
+}
+
+/// Shortest prefix that tells `mv` apart from other legal moves of the same kind to
+/// the same square: the file if it is unique, else the rank, else the whole square
+fn disambiguation<B: BB>(mv: Move, kind: PieceKind, pos: &Position<B>, gen: &MoveGenerator<B>) -> String {
+    let dims = &pos.dims;
+    let rivals: Vec<Square> = gen
+        .generate_legal(pos)
+        .into_iter()
+        .filter(|other| {
+            other.dst() == mv.dst()
+                && other.src() != mv.src()
+                && other.kind() != MoveType::Castling
+                && pos.piece_at(other.src()).map(|piece| piece.kind) == Some(kind)
+        })
+        .map(|other| other.src())
+        .collect();
+    if rivals.is_empty() {
+        return String::new();
+    }
+
+    let (file, rank) = mv.src().file_rank(dims);
+    if rivals.iter().all(|sq| sq.file_rank(dims).0 != file) {
+        ((b'a' + file) as char).to_string()
+    } else if rivals.iter().all(|sq| sq.file_rank(dims).1 != rank) {
+        (rank as u16 + 1).to_string()
+    } else {
+        format_square(mv.src(), dims)
+    }
+}
+
+/// Optional source file and rank written before the target square
+fn parse_disambiguation(text: &str, height: u8) -> Option<(Option<u8>, Option<u8>)> {
+    let (file, digits) = match text.chars().next() {
+        Some(ch) if ch.is_ascii_lowercase() => (Some(ch as u8 - b'a'), &text[1..]),
+        _ => (None, text),
+    };
+    let rank = match digits {
+        "" => None,
+        _ => {
+            let rank: u8 = digits.parse().ok()?;
+            if rank == 0 || rank > height {
+                return None;
+            }
+            Some(rank - 1)
+        }
+    };
+    Some((file, rank))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64, Dimensions};
+    use crate::movegen::patterns::{Direction, JumpingPattern};
+    use crate::piece::PieceDef;
+    use crate::position::Fen;
+
+    fn san_of(fen: &str, dims: Dimensions, uci: &str) -> String {
+        let pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        Move::from_uci(uci, &pos).unwrap().to_san(&pos, &gen)
+    }
+
+    #[test]
+    fn san_formats_standard_moves() {
+        let dims = Dimensions::standard();
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san_of(start, dims, "e2e4"), "e4");
+        assert_eq!(san_of(start, dims, "g1f3"), "Nf3");
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(san_of(kiwipete, dims, "e1g1"), "O-O");
+        assert_eq!(san_of(kiwipete, dims, "e1c1"), "O-O-O");
+        assert_eq!(san_of(kiwipete, dims, "d5e6"), "dxe6");
+        assert_eq!(san_of(kiwipete, dims, "e5f7"), "Nxf7");
+        assert_eq!(san_of(kiwipete, dims, "c3b5"), "Nb5");
+
+        assert_eq!(san_of("7k/8/8/K2pP3/8/8/8/8 w - d6 0 1", dims, "e5d6"), "exd6");
+        assert_eq!(san_of("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", dims, "g2h1n"), "gxh1=N");
+        assert_eq!(san_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", dims, "a1a8"), "Ra8#");
+        assert_eq!(san_of("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", dims, "a1a8"), "Ra8+");
+    }
+
+    #[test]
+    fn san_disambiguates_by_file_rank_or_square() {
+        let dims = Dimensions::standard();
+        // rooks on a1 and h1 share the rank, rooks on a1 and a5 share the file
+        let fen = "4k3/8/8/R7/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(san_of(fen, dims, "h1d1"), "Rhd1");
+        assert_eq!(san_of(fen, dims, "a1a3"), "R1a3");
+        assert_eq!(san_of(fen, dims, "a5a3"), "R5a3");
+
+        // queens on a1, a3 and c1 all reach b2
+        let fen = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+        assert_eq!(san_of(fen, dims, "a1b2"), "Qa1b2");
+        assert_eq!(san_of(fen, dims, "c1b2"), "Qcb2");
+    }
+
+    #[test]
+    fn san_round_trips_every_legal_move() {
+        let cases = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+        ];
+        let dims = Dimensions::standard();
+        let gen = MoveGenerator::new(dims);
+        for fen in cases {
+            let pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+            for mv in gen.generate_legal(&pos) {
+                let text = mv.to_san(&pos, &gen);
+                assert_eq!(Move::from_san(&text, &pos, &gen), Ok(mv), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn san_custom_pieces_on_large_board() {
+        let dims = Dimensions::new(12, 12);
+        let mut registry = PieceRegistry::standard();
+        let pattern = JumpingPattern::new(vec![
+            Direction { file_delta: 0, rank_delta: 9 },
+            Direction { file_delta: 1, rank_delta: 9 },
+            Direction { file_delta: -1, rank_delta: 9 },
+        ]);
+        registry.register(PieceDef::new(PieceKind::Custom(23), "leaper", "X").with_pattern(Box::new(pattern))).unwrap();
+        let step = JumpingPattern::new(vec![Direction::NORTH]);
+        registry.register(PieceDef::new(PieceKind::Custom(0), "tokin", "+P").with_pattern(Box::new(step))).unwrap();
+
+        let fen = "k11/12/12/12/12/12/12/12/12/12/12/X1X2+P5K w - - 0 1";
+        let pos: Position<BitBoard256> = Fen::parse_with(fen, dims, &registry).unwrap();
+        let gen = MoveGenerator::with_registry(dims, registry);
+
+        let mv = Move::from_san("Xab10", &pos, &gen).unwrap();
+        assert_eq!(mv.src(), Square::from_rank_file(0, 0, &dims));
+        assert_eq!(mv.to_san(&pos, &gen), "Xab10");
+        assert_eq!(Move::from_san("Xd10", &pos, &gen).unwrap().src(), Square::from_rank_file(0, 2, &dims));
+        assert_eq!(Move::from_san("Xb10", &pos, &gen), Err(NotationError::AmbiguousMove("Xb10".to_string())));
+        assert_eq!(Move::from_san("Zb10", &pos, &gen), Err(NotationError::UnknownPieceSymbol("Z".to_string())));
+
+        let tokin = Move::from_san("+Pf2", &pos, &gen).unwrap();
+        assert_eq!(tokin.src(), Square::from_rank_file(0, 5, &dims));
+        assert_eq!(tokin.to_san(&pos, &gen), "+Pf2");
+        assert_eq!(Move::from_san("f2=", &pos, &gen), Err(NotationError::InvalidMove("f2=".to_string())));
+    }
+
+    #[test]
+    fn san_drops() {
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("6k1/5ppp/8/8/8/8/8/4K3[RP] w - - 0 1", dims).unwrap();
+        let mut gen = MoveGenerator::new(dims);
+        gen.set_drop_rules(crate::movegen::DropRules::crazyhouse());
+
+        let mate = Move::from_san("R@e8", &pos, &gen).unwrap();
+        assert_eq!(mate.drop_kind(), Some(PieceKind::Rook));
+        assert_eq!(mate.to_san(&pos, &gen), "R@e8#");
+        assert_eq!(Move::from_san("@e4", &pos, &gen), Move::from_san("P@e4", &pos, &gen));
+        assert_eq!(Move::from_san("P@e8", &pos, &gen), Err(NotationError::IllegalMove("P@e8".to_string())));
+        assert_eq!(Move::from_san("N@f3", &pos, &gen), Err(NotationError::IllegalMove("N@f3".to_string())));
+    }
+
+    #[test]
+    fn san_parse_errors() {
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+
+        assert_eq!(Move::from_san("e5", &pos, &gen), Err(NotationError::IllegalMove("e5".to_string())));
+        assert_eq!(Move::from_san("O-O", &pos, &gen), Err(NotationError::IllegalMove("O-O".to_string())));
+        assert_eq!(Move::from_san("Nf", &pos, &gen), Err(NotationError::InvalidMove("Nf".to_string())));
+        assert_eq!(Move::from_san("Nf3+", &pos, &gen).map(|mv| mv.to_uci(&dims)), Ok("g1f3".to_string()));
+    }
+}
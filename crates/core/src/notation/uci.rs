@@ -0,0 +1,248 @@
+use crate::{
+    board::{Dimensions, BB},
+    error::MoveError,
+    moves::{Move, MoveType},
+    notation::{format_square, split_square, NotationError},
+    piece::{Color, Piece, PieceKind, PieceRegistry},
+    position::Position,
+};
+
+/// Long algebraic move text: source, target and the black symbol of a promotion
+/// piece, e.g. `e7e8q` or `a10a11n`. Drops are the white symbol, `@` and the
+/// target, e.g. `N@f3`. Variant actions follow the move: a gated piece as its black
+/// symbol, with the square it enters on unless that is the source (`b1c3h`,
+/// `e1g1hh1`), and a duck placement as a comma, the target and the duck's square
+/// (`e2e4,e4d5`). Teleports are written like ordinary moves.
+pub fn format_move<B>(mv: Move, dims: &Dimensions, registry: &PieceRegistry<B>) -> String {
+    let mut text = match mv.drop_kind() {
+        Some(kind) => {
+            let symbol = registry.symbol(Piece { color: Color::White, kind }).unwrap_or("?");
+            format!("{symbol}@{}", format_square(mv.dst(), dims))
+        }
+        None => format_square(mv.src(), dims) + &format_square(mv.dst(), dims),
+    };
+
+    if let Some(kind) = mv.promotion_kind() {
+        text.push_str(registry.symbol(Piece { color: Color::Black, kind }).unwrap_or("?"));
+    }
+    if let Some((kind, sq)) = mv.gating() {
+        text.push_str(registry.symbol(Piece { color: Color::Black, kind }).unwrap_or("?"));
+        if sq != mv.src() {
+            text.push_str(&format_square(sq, dims));
+        }
+    }
+    if let Some(sq) = mv.duck_square() {
+        text.push(',');
+        text.push_str(&format_square(mv.dst(), dims));
+        text.push_str(&format_square(sq, dims));
+    }
+    text
+}
+
+/// Read long algebraic move text against `pos`, which decides whether the move is a
+/// capture, castling, en passant or promotion. The result is checked with the same
+/// rules as `Position::try_make_move`. Promotion and gated pieces may be given by
+/// their symbol for either color; a symbol after a pawn move is a promotion, after
+/// any other move a gating.
+pub fn parse_move<B: BB>(text: &str, pos: &Position<B>, registry: &PieceRegistry<B>) -> Result<Move, NotationError> {
+    let dims = &pos.dims;
+    let invalid = || NotationError::InvalidMove(text.to_string());
+
+    let (primary, duck) = match text.split_once(',') {
+        Some((primary, duck)) => (primary, Some(duck)),
+        None => (text, None),
+    };
+    let mut mv = parse_primary(primary, pos, registry).map_err(|err| match err {
+        NotationError::InvalidMove(_) => invalid(),
+        err => err,
+    })?;
+
+    if let Some(duck) = duck {
+        let (from, rest) = split_square(duck, dims).ok_or_else(invalid)?;
+        match split_square(rest, dims) {
+            Some((sq, "")) if from == mv.dst() => mv = mv.with_duck(sq),
+            _ => return Err(invalid()),
+        }
+    }
+
+    pos.check_move(mv)?;
+    Ok(mv)
+}
+
+fn parse_primary<B: BB>(text: &str, pos: &Position<B>, registry: &PieceRegistry<B>) -> Result<Move, NotationError> {
+    let dims = &pos.dims;
+    let invalid = || NotationError::InvalidMove(text.to_string());
+    let unknown = |symbol: &str| NotationError::UnknownPieceSymbol(symbol.to_string());
+
+    if let Some((symbol, target)) = text.split_once('@') {
+        let kind = registry.piece_for_symbol(symbol).ok_or_else(|| unknown(symbol))?.kind;
+        return match split_square(target, dims) {
+            Some((sq, "")) => Ok(Move::new_drop(kind, sq)),
+            _ => Err(invalid()),
+        };
+    }
+
+    let (src, rest) = split_square(text, dims).ok_or_else(invalid)?;
+    let (dst, rest) = split_square(rest, dims).ok_or_else(invalid)?;
+    let piece = pos.piece_at(src).ok_or(MoveError::EmptySource(src.on(*dims)))?;
+
+    let (promotion, gating) = match rest {
+        "" => (None, None),
+        symbol if piece.kind == PieceKind::Pawn => {
+            (Some(registry.piece_for_symbol(symbol).ok_or_else(|| unknown(symbol))?.kind), None)
+        }
+        rest => {
+            let (gated, len) = registry.match_symbol(rest).ok_or_else(|| unknown(rest))?;
+            let gate_sq = match &rest[len..] {
+                "" => src,
+                square => match split_square(square, dims) {
+                    Some((sq, "")) => sq,
+                    _ => return Err(invalid()),
+                },
+            };
+            (None, Some((gated.kind, gate_sq)))
+        }
+    };
+
+    let (src_file, src_rank) = src.file_rank(dims);
+    let (dst_file, dst_rank) = dst.file_rank(dims);
+    let promotion_rank = match piece.color {
+        Color::White => dims.height - 1,
+        Color::Black => 0,
+    };
+
+    let mut mv = match (piece.kind, promotion) {
+        (_, Some(PieceKind::Pawn | PieceKind::King)) => {
+            return Err(MoveError::KindMismatch(MoveType::Promotion).into());
+        }
+        (_, Some(_)) if dst_rank != promotion_rank => return Err(invalid()),
+        (_, Some(kind)) => Move::new_promotion(src, dst, kind),
+        (PieceKind::Pawn, None) if dst_rank == promotion_rank => return Err(invalid()),
+        (PieceKind::Pawn, None) if src_file != dst_file && pos.ep_square == Some(dst) => {
+            Move::new(src, dst, MoveType::EnPassant)
+        }
+        (PieceKind::King, None) if src_rank == dst_rank && src_file.abs_diff(dst_file) == 2 => {
+            Move::new(src, dst, MoveType::Castling)
+        }
+        _ if pos.is_occupied_by(dst, piece.color.opposite()) => Move::new(src, dst, MoveType::Capture),
+        _ => Move::new(src, dst, MoveType::Quiet),
+    };
+
+    let victim = match mv.kind() {
+        MoveType::EnPassant => Some(PieceKind::Pawn),
+        MoveType::Castling => None,
+        _ => pos.piece_at(dst).filter(|victim| victim.color != piece.color).map(|victim| victim.kind),
+    };
+    if let Some(kind) = victim {
+        mv = mv.with_captured(kind);
+    }
+    if let Some((kind, sq)) = gating {
+        mv = mv.with_gating(kind, sq);
+    }
+    Ok(mv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64, Square};
+    use crate::movegen::MoveGenerator;
+    use crate::piece::PieceDef;
+    use crate::position::Fen;
+
+    #[test]
+    fn uci_round_trips_every_legal_move() {
+        let cases = [
+            (Dimensions::standard(), "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+            (Dimensions::standard(), "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+            (Dimensions::standard(), "7k/8/8/K2pP2r/8/8/8/8 w - d6 0 1"),
+        ];
+        for (dims, fen) in cases {
+            let pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+            let gen = MoveGenerator::new(dims);
+            for mv in gen.generate_pseudo_legal(&pos) {
+                let text = mv.to_uci(&dims);
+                assert_eq!(Move::from_uci(&text, &pos), Ok(mv), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn uci_multi_digit_ranks_and_custom_promotion() {
+        let dims = Dimensions::new(12, 12);
+        let pos: Position<BitBoard256> = Fen::parse("12/1P10/12/12/12/12/12/12/12/12/12/k10K w - - 0 1", dims).unwrap();
+        let mut registry = PieceRegistry::standard();
+        registry.register(PieceDef::new(PieceKind::Custom(23), "tokin", "+P")).unwrap();
+
+        let mv = parse_move("b11b12+p", &pos, &registry).unwrap();
+        assert_eq!(mv.promotion_kind(), Some(PieceKind::Custom(23)));
+        assert_eq!(format_move(mv, &dims, &registry), "b11b12+p");
+        assert_eq!(parse_move("b11b12+P", &pos, &registry), Ok(mv));
+        assert_eq!(Move::from_uci("b11b12x", &pos), Err(NotationError::UnknownPieceSymbol("x".to_string())));
+
+        let king = Move::from_uci("l1l2", &pos).unwrap();
+        assert_eq!(king.dst().file_rank(&dims), (11, 1));
+    }
+
+    #[test]
+    fn uci_drops() {
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/8/4K3[Nn] w - - 0 1", dims).unwrap();
+
+        let mv = Move::from_uci("N@f3", &pos).unwrap();
+        assert_eq!(mv.drop_kind(), Some(PieceKind::Knight));
+        assert_eq!(mv.to_uci(&dims), "N@f3");
+        assert_eq!(Move::from_uci("n@f3", &pos), Ok(mv));
+        assert_eq!(Move::from_uci("Q@f3", &pos), Err(NotationError::Move(MoveError::NotInPocket(PieceKind::Queen))));
+        assert_eq!(
+            Move::from_uci("N@e1", &pos),
+            Err(NotationError::Move(MoveError::BlockedTarget(Square::from_rank_file(0, 4, &dims).on(dims)))),
+        );
+        assert_eq!(Move::from_uci("N@", &pos), Err(NotationError::InvalidMove("N@".to_string())));
+    }
+
+    #[test]
+    fn uci_variant_actions_and_captured_kind() {
+        let dims = Dimensions::standard();
+        let sq = |text| Square::parse(text, &dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse("4k3/8/8/3p4/4P3/8/8/R3K1N1[Qq] w Q - 0 1", dims).unwrap();
+
+        let capture = Move::from_uci("e4d5", &pos).unwrap();
+        assert_eq!(capture.captured(), Some(PieceKind::Pawn));
+        assert_eq!(Move::from_uci("e4e5", &pos).unwrap().captured(), None);
+
+        let duck = Move::from_uci("e4d5,d5e4", &pos).unwrap();
+        assert_eq!((duck.primary(), duck.duck_square()), (capture, Some(sq("e4"))));
+        assert_eq!(duck.to_uci(&dims), "e4d5,d5e4");
+        assert_eq!(Move::from_uci("e4d5,e4e5", &pos), Err(NotationError::InvalidMove("e4d5,e4e5".to_string())));
+
+        let gating = Move::from_uci("g1f3q", &pos).unwrap();
+        assert_eq!(gating.gating(), Some((PieceKind::Queen, sq("g1"))));
+        assert_eq!(gating.to_uci(&dims), "g1f3q");
+        let castle = Move::from_uci("e1c1qa1", &pos).unwrap();
+        assert_eq!((castle.kind(), castle.gating()), (MoveType::Castling, Some((PieceKind::Queen, sq("a1")))));
+        assert_eq!(castle.to_uci(&dims), "e1c1qa1");
+        assert_eq!(Move::from_uci("e1c1qd1", &pos), Err(NotationError::Move(MoveError::BlockedTarget(sq("d1").on(dims)))));
+        assert_eq!(Move::from_uci("g1f3r", &pos), Err(NotationError::Move(MoveError::NotInPocket(PieceKind::Rook))));
+    }
+
+    #[test]
+    fn uci_rejects_bad_moves() {
+        let dims = Dimensions::standard();
+        let pos: Position<BitBoard64> = Fen::parse("4k3/P7/8/8/8/8/4P3/4K3 w - - 0 1", dims).unwrap();
+
+        assert_eq!(Move::from_uci("e1", &pos), Err(NotationError::InvalidMove("e1".to_string())));
+        assert_eq!(Move::from_uci("a7a8", &pos), Err(NotationError::InvalidMove("a7a8".to_string())));
+        assert_eq!(Move::from_uci("e2e3q", &pos), Err(NotationError::InvalidMove("e2e3q".to_string())));
+        assert_eq!(Move::from_uci("a7a8qq", &pos), Err(NotationError::UnknownPieceSymbol("qq".to_string())));
+        assert_eq!(Move::from_uci("a7a8?", &pos), Err(NotationError::UnknownPieceSymbol("?".to_string())));
+        assert_eq!(
+            Move::from_uci("a7a8k", &pos),
+            Err(NotationError::Move(MoveError::KindMismatch(MoveType::Promotion))),
+        );
+        assert_eq!(
+            Move::from_uci("d4d5", &pos),
+            Err(NotationError::Move(MoveError::EmptySource(crate::board::Square(27).on(dims)))),
+        );
+    }
+}
@@ -1,37 +1,40 @@
+use crate::board::BB;
 use crate::position::Position;
-use crate::{movegen::MoveGenerator};
+use crate::movegen::{MoveGenerator, MoveList};
+use crate::notation::uci;
 
-pub fn perft(pos: &Position, gen: &MoveGenerator, depth: u32) -> u64 {
+pub fn perft<B: BB>(pos: &mut Position<B>, gen: &MoveGenerator<B>, depth: u32) -> u64 {
     if depth == 0 {
         return 1;
     }
 
-    let moves = gen.generate_pseudo_legal(pos);
+    let mut moves = MoveList::new();
+    gen.generate_legal_into(pos, &mut moves);
     if depth == 1 {
         return moves.len() as u64;
     }
 
     let mut nodes = 0;
 
-    for m in moves {
-        let mut next = pos.clone();
-        next.make_move(m); // must NOT validate yet
-        nodes += perft(&next, gen, depth - 1);
+    for &m in &moves {
+        pos.make_move(m);
+        nodes += perft(pos, gen, depth - 1);
+        pos.unmake_move(m);
     }
 
     nodes
 }
 
 
-pub fn perft_divide(pos: &Position, gen: &MoveGenerator, depth: u32) -> Vec<(String, u64)> {
-    let moves = gen.generate_pseudo_legal(pos);
+pub fn perft_divide<B: BB>(pos: &mut Position<B>, gen: &MoveGenerator<B>, depth: u32) -> Vec<(String, u64)> {
+    let moves = gen.generate_legal(pos);
     let mut result = Vec::new();
 
     for m in moves {
-        let mut next = pos.clone();
-        next.make_move(m);
-        let count = perft(&next, gen, depth - 1);
-        result.push((m.to_uci(&pos.dims), count));
+        pos.make_move(m);
+        let count = perft(pos, gen, depth - 1);
+        pos.unmake_move(m);
+        result.push((uci::format_move(m, &pos.dims, gen.registry()), count));
     }
     result
 }
@@ -40,29 +43,142 @@ pub fn perft_divide(pos: &Position, gen: &MoveGenerator, depth: u32) -> Vec<(Str
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::board::{Dimensions};
+    use crate::board::{BitBoard256, BitBoard64, Dimensions};
+    use crate::movegen::GenType;
     use crate::position::Fen;
 
     #[test]
     fn perft_initial_position_depth_1() {
         let dims = Dimensions::standard();
-        let pos = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims).unwrap();
+        let mut pos: Position<BitBoard64> = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims).unwrap();
         let gen = MoveGenerator::new(dims);
 
-        let nodes = perft(&pos, &gen, 5);
+        let nodes = perft(&mut pos, &gen, 5);
         println!("nodes = {nodes}");
 
         assert!(nodes > 0);
     }
 
+    /// Positions from `test-data/perftsuite.epd` with their expected `(depth, nodes)` counts
+    pub(crate) fn perft_suite() -> Vec<(&'static str, Vec<(u32, u64)>)> {
+        include_str!("../../../test-data/perftsuite.epd")
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut fields = line.split(';');
+                let fen = fields.next().unwrap().trim();
+                let counts = fields
+                    .map(|field| {
+                        let (depth, nodes) = field.trim().split_once(' ').unwrap();
+                        (depth[1..].parse().unwrap(), nodes.parse().unwrap())
+                    })
+                    .collect();
+                (fen, counts)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn perft_suite_depth_1() {
+        let dims = Dimensions::standard();
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+
+        for (fen, counts) in perft_suite() {
+            let mut pos = Fen::parse(fen, dims).unwrap();
+            let expected = counts.iter().find(|(depth, _)| *depth == 1).unwrap().1;
+            assert_eq!(perft(&mut pos, &gen, 1), expected, "{fen}");
+        }
+    }
+
+    #[test]
+    fn perft_suite_depth_3() {
+        let dims = Dimensions::standard();
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+
+        for (fen, counts) in perft_suite() {
+            let mut pos = Fen::parse(fen, dims).unwrap();
+            for &(depth, expected) in counts.iter().filter(|(depth, _)| *depth <= 3) {
+                assert_eq!(perft(&mut pos, &gen, depth), expected, "{fen} depth {depth}");
+            }
+        }
+    }
+
+    /// Check the generation stages against `GenType::All` in `pos` and the positions
+    /// below it
+    fn check_stages<B: BB>(pos: &mut Position<B>, gen: &MoveGenerator<B>, depth: u32, fen: &str) {
+        let all = gen.generate(pos, GenType::All);
+        let captures = gen.generate(pos, GenType::Captures);
+        let quiets = gen.generate(pos, GenType::Quiets);
+        let mut staged: Vec<_> = captures.iter().chain(&quiets).copied().collect();
+        assert_eq!(staged.len(), all.len(), "{fen}");
+        staged.retain(|mv| all.contains(mv));
+        assert_eq!(staged.len(), all.len(), "{fen}");
+
+        let evasions = gen.generate(pos, GenType::Evasions);
+        assert!(evasions.iter().all(|mv| all.contains(mv)), "{fen}");
+        let legal = gen.generate_legal(pos);
+        let legal_evasions: Vec<_> = evasions.into_iter().filter(|&mv| gen.is_legal(pos, mv)).collect();
+        assert_eq!(legal_evasions, legal, "{fen}");
+
+        if depth > 1 {
+            for mv in legal {
+                pos.make_move(mv);
+                check_stages(pos, gen, depth - 1, fen);
+                pos.unmake_move(mv);
+            }
+        }
+    }
+
+    #[test]
+    fn staged_generation_sums_to_pseudo_legal_on_perft_suite() {
+        let dims = Dimensions::standard();
+        let gen: MoveGenerator<BitBoard64> = MoveGenerator::new(dims);
+
+        for (fen, _) in perft_suite() {
+            let mut pos = Fen::parse(fen, dims).unwrap();
+            check_stages(&mut pos, &gen, 2, fen);
+        }
+    }
+
+    #[test]
+    fn perft_capablanca_with_compound_presets() {
+        use crate::movegen::CompoundPattern;
+        use crate::piece::{PieceDef, PieceKind, PieceRegistry};
+
+        let dims = Dimensions::new(10, 8);
+        let mut registry = PieceRegistry::standard();
+        registry.register(
+            PieceDef::new(PieceKind::Custom(0), "archbishop", "A").with_pattern(Box::new(CompoundPattern::archbishop())),
+        ).unwrap();
+        registry.register(
+            PieceDef::new(PieceKind::Custom(1), "chancellor", "C").with_pattern(Box::new(CompoundPattern::chancellor())),
+        ).unwrap();
+        let gen: MoveGenerator<BitBoard256> = MoveGenerator::with_registry(dims, registry);
+
+        let fen = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1";
+        let mut pos = Fen::parse_with(fen, dims, gen.registry()).unwrap();
+        for (depth, expected) in [(1, 28), (2, 784), (3, 25228)] {
+            assert_eq!(perft(&mut pos, &gen, depth), expected, "depth {depth}");
+        }
+
+        let fen = "r4k3r/pppacppppp/10/10/10/10/PPPACPPPPP/R4K3R w KQkq - 0 1";
+        let mut pos = Fen::parse_with(fen, dims, gen.registry()).unwrap();
+        // both kings castle from the f-file, two squares towards either rook
+        let castles: Vec<_> = perft_divide(&mut pos, &gen, 1).into_iter().map(|(uci, _)| uci).filter(|uci| uci.starts_with("f1")).collect();
+        assert_eq!(castles, ["f1e1", "f1g1", "f1h1", "f1d1"]);
+        for (depth, expected) in [(1, 54), (2, 2827), (3, 142429)] {
+            assert_eq!(perft(&mut pos, &gen, depth), expected, "depth {depth}");
+        }
+        check_stages(&mut pos, &gen, 2, fen);
+    }
+
     #[test]
     fn perft_initial_position_depth_2() {
         let dims = Dimensions::new(8,11);
-        let pos = Fen::parse("r6k/8/8/8/8/8/8/8/8/8/1R2K3 w - - 0 1", dims).unwrap();
+        let mut pos: Position<BitBoard256> = Fen::parse("r6k/8/8/8/8/8/8/8/8/8/1R2K3 w - - 0 1", dims).unwrap();
         let gen = MoveGenerator::new(dims);
-        let moves = gen.generate_pseudo_legal(&pos);
         
-        let nodes = perft(&pos, &gen, 3);
+        let nodes = perft(&mut pos, &gen, 3);
         println!("nodes = {nodes}");
 
         assert!(nodes > 0);
@@ -0,0 +1,73 @@
+pub mod registry;
+
+pub use registry::{PieceDef, PieceRegistry, RegistryError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    #[inline]
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+/// In JSON the standard kinds are lowercase names such as `"knight"`, and custom
+/// kinds are `{"custom": id}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+    Custom(u8),
+}
+
+impl PieceKind {
+    /// Number of built-in kinds; `Custom(id)` kinds are indexed after them
+    pub const STANDARD_COUNT: usize = 6;
+    
+    /// Dense index: 0..6 for the standard kinds, `6 + id` for `Custom(id)`
+    #[inline]
+    pub const fn index(self) -> usize {
+        match self {
+            PieceKind::Pawn => 0,
+            PieceKind::Knight => 1,
+            PieceKind::Bishop => 2,
+            PieceKind::Rook => 3,
+            PieceKind::Queen => 4,
+            PieceKind::King => 5,
+            PieceKind::Custom(id) => Self::STANDARD_COUNT + id as usize,
+        }
+    }
+    
+    #[inline]
+    pub fn from_index(idx: usize) -> Self {
+        match idx {
+            0 => PieceKind::Pawn,
+            1 => PieceKind::Knight,
+            2 => PieceKind::Bishop,
+            3 => PieceKind::Rook,
+            4 => PieceKind::Queen,
+            5 => PieceKind::King,
+            _ => PieceKind::Custom((idx - Self::STANDARD_COUNT) as u8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Piece {
+    pub color: Color,
+    pub kind: PieceKind,
+}
@@ -0,0 +1,305 @@
+use std::fmt;
+
+use crate::{
+    board::{BitBoard, BB},
+    movegen::MovePattern,
+    notation::betza::{self, BetzaError},
+    piece::{Color, Piece, PieceKind},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// Empty, starting with a digit, containing `/`, `.` or whitespace, or with a first
+    /// letter in the wrong case for its color
+    InvalidSymbol(String),
+    /// The symbol already names another piece
+    DuplicateSymbol(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::InvalidSymbol(symbol) => write!(f, "invalid piece symbol '{symbol}'"),
+            RegistryError::DuplicateSymbol(symbol) => write!(f, "piece symbol '{symbol}' is already taken"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Everything the crate knows about one piece kind.
+///
+/// Symbols are what FEN, SAN and UCI text use for the piece, indexed by color. They
+/// may be longer than one character, e.g. `+P` for a promoted pawn; an empty symbol
+/// means the piece has no text form. `pattern` is `None` for the built-in kinds,
+/// which the move generator handles itself.
+pub struct PieceDef<B = BitBoard> {
+    pub kind: PieceKind,
+    pub name: String,
+    pub symbols: [String; 2],
+    /// Material value in centipawns
+    pub value: i32,
+    pub pattern: Option<Box<dyn MovePattern<B>>>,
+    /// Betza text `pattern` was built from, which PGN records store for the piece
+    pub betza: Option<String>,
+}
+
+impl<B> PieceDef<B> {
+    /// Piece written as `symbol` for white and as its lower-case form for black
+    pub fn new(kind: PieceKind, name: &str, symbol: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            symbols: [symbol.to_string(), symbol.to_lowercase()],
+            value: 0,
+            pattern: None,
+            betza: None,
+        }
+    }
+
+    pub fn with_black_symbol(mut self, symbol: &str) -> Self {
+        self.symbols[Color::Black as usize] = symbol.to_string();
+        self
+    }
+
+    pub fn with_value(mut self, value: i32) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: Box<dyn MovePattern<B>>) -> Self {
+        self.pattern = Some(pattern);
+        self.betza = None;
+        self
+    }
+
+    pub fn symbol(&self, color: Color) -> &str {
+        &self.symbols[color as usize]
+    }
+}
+
+impl<B: BB> PieceDef<B> {
+    /// Move like the Betza definition `text`, e.g. `WfF`, and keep the text
+    pub fn with_betza(mut self, text: &str) -> Result<Self, BetzaError> {
+        self.pattern = Some(Box::new(betza::parse_pattern(text)?));
+        self.betza = Some(text.trim().to_string());
+        Ok(self)
+    }
+}
+
+/// The piece kinds a game uses, looked up by kind or by symbol.
+///
+/// Text formats only accept symbols registered here, and the move generator only
+/// moves custom kinds that have a pattern here.
+pub struct PieceRegistry<B = BitBoard> {
+    /// Indexed by `PieceKind::index`
+    defs: Vec<Option<PieceDef<B>>>,
+}
+
+impl<B> PieceRegistry<B> {
+    pub fn empty() -> Self {
+        Self { defs: Vec::new() }
+    }
+
+    /// The six chess pieces with their usual letters and values
+    pub fn standard() -> Self {
+        let mut registry = Self::empty();
+        for (kind, name, symbol, value) in [
+            (PieceKind::Pawn, "pawn", "P", 100),
+            (PieceKind::Knight, "knight", "N", 300),
+            (PieceKind::Bishop, "bishop", "B", 300),
+            (PieceKind::Rook, "rook", "R", 500),
+            (PieceKind::Queen, "queen", "Q", 900),
+            (PieceKind::King, "king", "K", 0),
+        ] {
+            registry.insert(PieceDef::new(kind, name, symbol).with_value(value));
+        }
+        registry
+    }
+
+    /// Add `def`, replacing any earlier definition of the same kind
+    pub fn register(&mut self, def: PieceDef<B>) -> Result<(), RegistryError> {
+        for color in [Color::White, Color::Black] {
+            let symbol = def.symbol(color);
+            if symbol.is_empty() {
+                continue;
+            }
+            if !is_valid_symbol(symbol, color) {
+                return Err(RegistryError::InvalidSymbol(symbol.to_string()));
+            }
+            let taken = self
+                .iter()
+                .filter(|other| other.kind != def.kind)
+                .any(|other| other.symbols.iter().any(|s| s == symbol));
+            if taken || def.symbol(color.opposite()) == symbol {
+                return Err(RegistryError::DuplicateSymbol(symbol.to_string()));
+            }
+        }
+        self.insert(def);
+        Ok(())
+    }
+
+    /// Give `kind` a move pattern. Kinds that are not registered yet are added without
+    /// symbols, so they move but cannot be read from or written to text.
+    pub fn set_pattern(&mut self, kind: PieceKind, pattern: Box<dyn MovePattern<B>>) {
+        match self.get_mut(kind) {
+            Some(def) => {
+                def.pattern = Some(pattern);
+                def.betza = None;
+            }
+            None => self.insert(PieceDef {
+                kind,
+                name: format!("{kind:?}"),
+                symbols: [String::new(), String::new()],
+                value: 0,
+                pattern: Some(pattern),
+                betza: None,
+            }),
+        }
+    }
+
+    pub fn get(&self, kind: PieceKind) -> Option<&PieceDef<B>> {
+        self.defs.get(kind.index()).and_then(Option::as_ref)
+    }
+
+    fn get_mut(&mut self, kind: PieceKind) -> Option<&mut PieceDef<B>> {
+        self.defs.get_mut(kind.index()).and_then(Option::as_mut)
+    }
+
+    pub fn contains(&self, kind: PieceKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// Registered definitions in `PieceKind::index` order
+    pub fn iter(&self) -> impl Iterator<Item = &PieceDef<B>> {
+        self.defs.iter().flatten()
+    }
+
+    #[inline]
+    pub fn pattern(&self, kind: PieceKind) -> Option<&dyn MovePattern<B>> {
+        self.get(kind).and_then(|def| def.pattern.as_deref())
+    }
+
+    pub fn name(&self, kind: PieceKind) -> Option<&str> {
+        self.get(kind).map(|def| def.name.as_str())
+    }
+
+    pub fn value(&self, kind: PieceKind) -> Option<i32> {
+        self.get(kind).map(|def| def.value)
+    }
+
+    pub fn symbol(&self, piece: Piece) -> Option<&str> {
+        self.get(piece.kind)
+            .map(|def| def.symbol(piece.color))
+            .filter(|symbol| !symbol.is_empty())
+    }
+
+    /// The piece written exactly as `text`
+    pub fn piece_for_symbol(&self, text: &str) -> Option<Piece> {
+        self.symbols().find(|&(_, symbol)| symbol == text).map(|(piece, _)| piece)
+    }
+
+    /// The piece whose symbol is the longest prefix of `text`, with the symbol length in bytes
+    pub fn match_symbol(&self, text: &str) -> Option<(Piece, usize)> {
+        self.symbols()
+            .filter(|(_, symbol)| text.starts_with(symbol))
+            .max_by_key(|(_, symbol)| symbol.len())
+            .map(|(piece, symbol)| (piece, symbol.len()))
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = (Piece, &str)> {
+        self.iter().flat_map(|def| {
+            [Color::White, Color::Black]
+                .into_iter()
+                .map(move |color| (Piece { color, kind: def.kind }, def.symbol(color)))
+                .filter(|(_, symbol)| !symbol.is_empty())
+        })
+    }
+
+    fn insert(&mut self, def: PieceDef<B>) {
+        let idx = def.kind.index();
+        if self.defs.len() <= idx {
+            self.defs.resize_with(idx + 1, || None);
+        }
+        self.defs[idx] = Some(def);
+    }
+}
+
+impl<B> Default for PieceRegistry<B> {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// Symbols must survive the FEN board field and tell the colors apart by the case of
+/// their first letter
+fn is_valid_symbol(symbol: &str, color: Color) -> bool {
+    let Some(letter) = symbol.chars().find(char::is_ascii_alphabetic) else {
+        return false;
+    };
+    let case_ok = match color {
+        Color::White => letter.is_ascii_uppercase(),
+        Color::Black => letter.is_ascii_lowercase(),
+    };
+    case_ok
+        && !symbol.starts_with(|ch: char| ch.is_ascii_digit())
+        && symbol.chars().all(|ch| ch.is_ascii_graphic() && ch != '/' && ch != '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BitBoard64;
+    use crate::movegen::patterns::{Direction, JumpingPattern};
+
+    #[test]
+    fn registry_looks_up_multi_character_symbols() {
+        let mut registry: PieceRegistry<BitBoard64> = PieceRegistry::standard();
+        registry
+            .register(PieceDef::new(PieceKind::Custom(0), "tokin", "+P").with_value(400))
+            .unwrap();
+        registry.register(PieceDef::new(PieceKind::Custom(1), "cannon", "Ca")).unwrap();
+
+        let tokin = Piece { color: Color::Black, kind: PieceKind::Custom(0) };
+        assert_eq!(registry.symbol(tokin), Some("+p"));
+        assert_eq!(registry.piece_for_symbol("+p"), Some(tokin));
+        assert_eq!(registry.value(PieceKind::Custom(0)), Some(400));
+
+        let cannon = Piece { color: Color::White, kind: PieceKind::Custom(1) };
+        assert_eq!(registry.match_symbol("Ca3"), Some((cannon, 2)));
+        assert_eq!(registry.match_symbol("p2"), Some((Piece { color: Color::Black, kind: PieceKind::Pawn }, 1)));
+        assert_eq!(registry.match_symbol("X"), None);
+        assert_eq!(registry.name(PieceKind::Knight), Some("knight"));
+    }
+
+    #[test]
+    fn registry_rejects_bad_and_duplicate_symbols() {
+        let mut registry: PieceRegistry<BitBoard64> = PieceRegistry::standard();
+        for bad in ["", "1A", "A.", "A/", "a", "+"] {
+            let def = PieceDef::new(PieceKind::Custom(0), "bad", "X").with_black_symbol("x");
+            let def = PieceDef { symbols: [bad.to_string(), "x".to_string()], ..def };
+            if bad.is_empty() {
+                assert!(registry.register(def).is_ok());
+            } else {
+                assert_eq!(registry.register(def), Err(RegistryError::InvalidSymbol(bad.to_string())));
+            }
+        }
+        assert_eq!(
+            registry.register(PieceDef::new(PieceKind::Custom(1), "archbishop", "N")),
+            Err(RegistryError::DuplicateSymbol("N".to_string())),
+        );
+        // redefining a kind may keep its own symbols
+        assert!(registry.register(PieceDef::new(PieceKind::Knight, "horse", "N")).is_ok());
+        assert_eq!(registry.name(PieceKind::Knight), Some("horse"));
+    }
+
+    #[test]
+    fn registry_set_pattern_adds_symbolless_kind() {
+        let mut registry: PieceRegistry<BitBoard64> = PieceRegistry::standard();
+        registry.set_pattern(PieceKind::Custom(5), Box::new(JumpingPattern::new(vec![Direction::NORTH])));
+
+        assert!(registry.pattern(PieceKind::Custom(5)).is_some());
+        assert!(registry.pattern(PieceKind::Rook).is_none());
+        assert_eq!(registry.symbol(Piece { color: Color::White, kind: PieceKind::Custom(5) }), None);
+    }
+}
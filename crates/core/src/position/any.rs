@@ -0,0 +1,113 @@
+use crate::{
+    board::{BitBoard256, BitBoard64, Dimensions, BB},
+    position::{Fen, FenError, Position},
+    error::DimensionsError,
+};
+
+/// A position whose bitboard type is chosen from its dimensions when it is built.
+///
+/// Boards of up to 64 squares use `BitBoard64`, anything larger `BitBoard256`.
+/// Code that works on the inner `Position` (usually through `with_position!`) is
+/// monomorphized for each case, so no per-operation size dispatch remains.
+#[derive(Debug, Clone)]
+pub enum AnyPosition {
+    Small(Position<BitBoard64>),
+    Large(Position<BitBoard256>),
+}
+
+impl AnyPosition {
+    pub fn new_empty(dims: Dimensions) -> Self {
+        if dims.num_squares() <= BitBoard64::MAX_SQUARES {
+            AnyPosition::Small(Position::new_empty(dims))
+        } else {
+            AnyPosition::Large(Position::new_empty(dims))
+        }
+    }
+    
+    pub fn try_new_empty(dims: Dimensions) -> Result<Self, DimensionsError> {
+        if dims.num_squares() <= BitBoard64::MAX_SQUARES {
+            Ok(AnyPosition::Small(Position::try_new_empty(dims)?))
+        } else {
+            Ok(AnyPosition::Large(Position::try_new_empty(dims)?))
+        }
+    }
+    
+    pub fn from_fen(input: &str, dims: Dimensions) -> Result<Self, FenError> {
+        if dims.num_squares() <= BitBoard64::MAX_SQUARES {
+            Ok(AnyPosition::Small(Fen::parse(input, dims)?))
+        } else {
+            Ok(AnyPosition::Large(Fen::parse(input, dims)?))
+        }
+    }
+    
+    pub fn dims(&self) -> Dimensions {
+        crate::with_position!(self, pos => pos.dims)
+    }
+    
+    pub fn to_fen(&self) -> Result<String, FenError> {
+        crate::with_position!(self, pos => Fen::to_string(pos))
+    }
+}
+
+/// Bind the concrete `Position` inside an `AnyPosition` and evaluate `$body` with it.
+///
+/// ```
+/// use sf_core::{with_position, board::Dimensions, movegen::MoveGenerator, perft::perft};
+/// use sf_core::position::AnyPosition;
+///
+/// let dims = Dimensions::standard();
+/// let mut any = AnyPosition::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1", dims).unwrap();
+/// let nodes = with_position!(&mut any, pos => {
+///     let gen = MoveGenerator::new(dims);
+///     perft(pos, &gen, 2)
+/// });
+/// assert_eq!(nodes, 66);
+/// ```
+#[macro_export]
+macro_rules! with_position {
+    ($any:expr, $pos:ident => $body:expr) => {
+        match $any {
+            $crate::position::AnyPosition::Small($pos) => $body,
+            $crate::position::AnyPosition::Large($pos) => $body,
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+    use crate::perft::perft;
+
+    #[test]
+    fn any_position_picks_bitboard_by_size() {
+        let small = AnyPosition::new_empty(Dimensions::standard());
+        let large = AnyPosition::new_empty(Dimensions::new(10, 8));
+        
+        assert!(matches!(small, AnyPosition::Small(_)));
+        assert!(matches!(large, AnyPosition::Large(_)));
+        assert_eq!(large.dims(), Dimensions::new(10, 8));
+    }
+    
+    #[test]
+    fn any_position_perft_matches_on_both_sizes() {
+        let cases = [
+            (Dimensions::standard(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"),
+            (Dimensions::new(8, 9), "r3k2r/8/8/8/8/8/8/8/R3K2R w KQkq - 0 1"),
+        ];
+        
+        for (dims, fen) in cases {
+            let mut any = AnyPosition::from_fen(fen, dims).unwrap();
+            let mut reference: Position = Fen::parse(fen, dims).unwrap();
+            
+            let nodes = crate::with_position!(&mut any, pos => {
+                let gen = MoveGenerator::new(dims);
+                perft(pos, &gen, 3)
+            });
+            let expected = perft(&mut reference, &MoveGenerator::new(dims), 3);
+            
+            assert_eq!(nodes, expected);
+            assert_eq!(any.to_fen().unwrap(), fen);
+        }
+    }
+}
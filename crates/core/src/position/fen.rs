@@ -1,132 +1,222 @@
+use std::fmt;
+
 use crate::{
-    board::{Dimensions, Square},
-    piece::{PieceKind, Color, Piece},
+    board::{Dimensions, Square, BB},
+    piece::{Color, Piece, PieceRegistry},
     position::Position,
+    error::DimensionsError,
+    notation,
 };
 
+/// FEN fields, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenField {
+    Board,
+    ActiveColor,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+/// FEN parse error. Board positions are given as `row` (0 = first row written,
+/// i.e. the highest rank) and `col` (0 = the a-file).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FenError {
-    InvalidFormat,
+    /// Fewer than the four required fields
+    MissingFields { got: usize },
     RowCountMismatch { expected: u8, got: u8 },
-    ColCountMismatch { expected: u8, got: u8 },
-    UnknownPieceSymbol(char),
-    InvalidActiveColor,
-    InvalidEnPassant,
-    InvalidCastling,
-    InvalidNumber(String),
+    ColCountMismatch { row: u8, expected: u8, got: u16 },
+    UnknownPieceSymbol { row: u8, col: u8, symbol: char },
+    InvalidActiveColor(String),
+    InvalidEnPassant(String),
+    InvalidCastling(char),
+    /// Malformed `[...]` holdings section
+    InvalidHoldings(String),
+    InvalidNumber { field: FenField, value: String },
+    Dimensions(DimensionsError),
+    /// A piece on the board or in a pocket that the registry gives no symbol to write
+    UnknownPiece(Piece),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingFields { got } => {
+                write!(f, "FEN needs at least 4 fields, got {got}")
+            }
+            FenError::RowCountMismatch { expected, got } => {
+                write!(f, "FEN has {got} rows, expected {expected}")
+            }
+            FenError::ColCountMismatch { row, expected, got } => {
+                write!(f, "FEN row {} covers {got} files, expected {expected}", row + 1)
+            }
+            FenError::UnknownPieceSymbol { row, col, symbol } => {
+                write!(f, "unknown piece symbol '{symbol}' in FEN row {}, column {}", row + 1, col + 1)
+            }
+            FenError::InvalidActiveColor(value) => write!(f, "invalid active color '{value}'"),
+            FenError::InvalidEnPassant(value) => write!(f, "invalid en passant square '{value}'"),
+            FenError::InvalidCastling(ch) => write!(f, "invalid castling flag '{ch}'"),
+            FenError::InvalidHoldings(value) => write!(f, "invalid holdings '{value}'"),
+            FenError::InvalidNumber { field, value } => write!(f, "invalid number '{value}' in {field:?} field"),
+            FenError::Dimensions(err) => err.fmt(f),
+            FenError::UnknownPiece(piece) => {
+                write!(f, "no FEN symbol for {:?} {:?}", piece.color, piece.kind)
+            }
+        }
+    }
 }
 
-/// Standard chess piece symbols
-fn piece_from_symbol(ch: char) -> Option<(Color, PieceKind)> {
-    match ch {
-        'P' => Some((Color::White, PieceKind::Pawn)),
-        'N' => Some((Color::White, PieceKind::Knight)),
-        'B' => Some((Color::White, PieceKind::Bishop)),
-        'R' => Some((Color::White, PieceKind::Rook)),
-        'Q' => Some((Color::White, PieceKind::Queen)),
-        'K' => Some((Color::White, PieceKind::King)),
-        'p' => Some((Color::Black, PieceKind::Pawn)),
-        'n' => Some((Color::Black, PieceKind::Knight)),
-        'b' => Some((Color::Black, PieceKind::Bishop)),
-        'r' => Some((Color::Black, PieceKind::Rook)),
-        'q' => Some((Color::Black, PieceKind::Queen)),
-        'k' => Some((Color::Black, PieceKind::King)),
-        _ => None,
+impl std::error::Error for FenError {}
+
+impl From<DimensionsError> for FenError {
+    fn from(err: DimensionsError) -> Self {
+        FenError::Dimensions(err)
     }
 }
 
+/// Marks a wall (non-playable square) in the board field
+const WALL_SYMBOL: char = '.';
+/// Follows the symbol of a promoted piece in games with holdings
+const PROMOTED_SUFFIX: char = '~';
+
+/// Length of a run of empty squares in the board field
+fn parse_empty_run(digits: &str) -> Result<u16, FenError> {
+    digits.parse().map_err(|_| FenError::InvalidNumber {
+        field: FenField::Board,
+        value: digits.to_string(),
+    })
+}
+
 pub struct Fen;
 
 impl Fen {
     /// Parse a FEN string for standard chess
     /// Format: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
-    pub fn parse(input: &str, dims: Dimensions) -> Result<Position, FenError> {
+    /// Walls are written as `.` in the board field. Pockets follow the board field in
+    /// brackets, as in `.../RNBQKBNR[Qnp] w`, and a `~` after a piece marks it as promoted;
+    /// a holdings section (even `[]`) turns on `Position::holdings`.
+    pub fn parse<B: BB>(input: &str, dims: Dimensions) -> Result<Position<B>, FenError> {
+        Self::parse_with(input, dims, &PieceRegistry::standard())
+    }
+
+    /// Parse a FEN string whose pieces are the symbols in `registry`. Where symbols
+    /// overlap, the longest one wins.
+    pub fn parse_with<B: BB>(
+        input: &str,
+        dims: Dimensions,
+        registry: &PieceRegistry<B>,
+    ) -> Result<Position<B>, FenError> {
         let parts: Vec<&str> = input.split_whitespace().collect();
         if parts.len() < 4 {
-            return Err(FenError::InvalidFormat);
+            return Err(FenError::MissingFields { got: parts.len() });
         }
 
-        let board_part = parts[0];
-        let active_color_part = parts.get(1).copied().unwrap_or("w");
-        let castling_part = parts.get(2).copied().unwrap_or("-");
-        let ep_part = parts.get(3).copied().unwrap_or("-");
+        let (board_part, holdings_part) = match parts[0].split_once('[') {
+            Some((board, holdings)) => {
+                let holdings = holdings
+                    .strip_suffix(']')
+                    .ok_or_else(|| FenError::InvalidHoldings(holdings.to_string()))?;
+                (board, Some(holdings))
+            }
+            None => (parts[0], None),
+        };
+        let active_color_part = parts[1];
+        let castling_part = parts[2];
+        let ep_part = parts[3];
         let halfmove_part = parts.get(4).unwrap_or(&"0");
         let fullmove_part = parts.get(5).unwrap_or(&"1");
 
         let rows: Vec<&str> = board_part.split('/').collect();
-        if rows.len() as u8 != dims.height {
+        if rows.len() != dims.height as usize {
             return Err(FenError::RowCountMismatch {
                 expected: dims.height,
-                got: rows.len() as u8,
+                got: rows.len().min(u8::MAX as usize) as u8,
             });
         }
 
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<B> = Position::try_new_empty(dims)?;
 
-        for (rank_idx, row) in rows.iter().enumerate() {
-            let rank = (dims.height - 1 - rank_idx as u8) as u8;
-            let mut file: u8 = 0;
-            let mut digit_buffer = String::new();
+        for (row_idx, row) in rows.iter().enumerate() {
+            let row_idx = row_idx as u8;
+            let rank = dims.height - 1 - row_idx;
+            // u16 so that oversized rows are reported instead of wrapping
+            let mut file: u16 = 0;
+            let mut rest = *row;
 
-            for ch in row.chars() {
+            while let Some(ch) = rest.chars().next() {
                 if ch.is_ascii_digit() {
-                    digit_buffer.push(ch);
+                    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+                    let run = parse_empty_run(&rest[..digits])?;
+                    file = file.checked_add(run).filter(|&end| end <= dims.width as u16).ok_or(
+                        FenError::ColCountMismatch {
+                            row: row_idx,
+                            expected: dims.width,
+                            got: file.saturating_add(run),
+                        },
+                    )?;
+                    rest = &rest[digits..];
                     continue;
                 }
 
-                if !digit_buffer.is_empty() {
-                    let count: u8 = digit_buffer.parse()
-                        .map_err(|_| FenError::InvalidNumber(digit_buffer.clone()))?;
-                    file += count;
-                    digit_buffer.clear();
-                }
-
-                if file >= dims.width {
+                if file >= dims.width as u16 {
                     return Err(FenError::ColCountMismatch {
+                        row: row_idx,
                         expected: dims.width,
                         got: file + 1,
                     });
                 }
+                let sq = Square::from_rank_file(rank, file as u8, &dims);
 
-                match piece_from_symbol(ch) {
-                    Some((color, kind)) => {
-                        let sq = Square::from_rank_file(rank, file, &dims);
-                        pos.set_piece(sq, Piece { color, kind });
-                    }
-                    None => {
-                        let color = if ch.is_uppercase() {
-                            Color::White
-                        } else {
-                            Color::Black
-                        };
-                        let custom_id = (ch as u8) % 128;
-                        let kind = PieceKind::Custom(custom_id);
-                        let sq = Square::from_rank_file(rank, file, &dims);
-                        pos.set_piece(sq, Piece { color, kind });
+                if ch == WALL_SYMBOL {
+                    pos.set_wall(sq);
+                    rest = &rest[1..];
+                } else {
+                    let Some((piece, len)) = registry.match_symbol(rest) else {
+                        return Err(FenError::UnknownPieceSymbol {
+                            row: row_idx,
+                            col: file as u8,
+                            symbol: ch,
+                        });
+                    };
+                    pos.set_piece(sq, piece);
+                    rest = &rest[len..];
+                    if let Some(after) = rest.strip_prefix(PROMOTED_SUFFIX) {
+                        pos.promoted = pos.promoted.set(sq);
+                        rest = after;
                     }
                 }
-
                 file += 1;
             }
-                        if !digit_buffer.is_empty() {
-                let count: u8 = digit_buffer.parse()
-                    .map_err(|_| FenError::InvalidNumber(digit_buffer.clone()))?;
-                file += count;
-            }
 
-            if file != dims.width {
+            if file != dims.width as u16 {
                 return Err(FenError::ColCountMismatch {
+                    row: row_idx,
                     expected: dims.width,
                     got: file,
                 });
             }
         }
 
+        if let Some(holdings) = holdings_part {
+            pos.holdings = true;
+            let mut rest = if holdings == "-" { "" } else { holdings };
+            while !rest.is_empty() {
+                let (piece, len) = registry
+                    .match_symbol(rest)
+                    .ok_or_else(|| FenError::InvalidHoldings(holdings.to_string()))?;
+                if !pos.add_to_pocket(piece.color, piece.kind) {
+                    return Err(FenError::InvalidHoldings(holdings.to_string()));
+                }
+                rest = &rest[len..];
+            }
+        }
+
         // Parse active color
         match active_color_part {
             "w" | "W" => pos.side_to_move = Color::White,
             "b" | "B" => pos.side_to_move = Color::Black,
-            _ => return Err(FenError::InvalidActiveColor),
+            _ => return Err(FenError::InvalidActiveColor(active_color_part.to_string())),
         }
 
         // Parse castling rights
@@ -137,41 +227,41 @@ impl Fen {
                     'Q' => pos.castling_rights.set_white_queenside(true),
                     'k' => pos.castling_rights.set_black_kingside(true),
                     'q' => pos.castling_rights.set_black_queenside(true),
-                    _ => {}
+                    _ => return Err(FenError::InvalidCastling(ch)),
                 }
             }
+            pos.detect_castling_king_files();
         }
 
         if ep_part != "-" {
-            if ep_part.len() == 2 {
-                let mut chars = ep_part.chars();
-                let file_char = chars.next().unwrap();
-                let rank_char = chars.next().unwrap();
-                
-                if file_char.is_ascii_lowercase() && rank_char.is_ascii_digit() {
-                    let file = (file_char as u8) - b'a';
-                    let rank = (rank_char as u8) - b'1';
-                    
-                    if file < dims.width && rank < dims.height {
-                        pos.ep_square = Some(Square::from_rank_file(rank, file, &dims));
-                    }
-                }
-            }
+            let ep_square = notation::parse_square(ep_part, &dims)
+                .map_err(|_| FenError::InvalidEnPassant(ep_part.to_string()))?;
+            pos.ep_square = Some(ep_square);
         }
 
-        pos.halfmove_clock = halfmove_part
-            .parse()
-            .map_err(|_| FenError::InvalidNumber(halfmove_part.to_string()))?;
+        pos.halfmove_clock = halfmove_part.parse().map_err(|_| FenError::InvalidNumber {
+            field: FenField::HalfmoveClock,
+            value: halfmove_part.to_string(),
+        })?;
 
-        pos.fullmove_number = fullmove_part
-            .parse()
-            .map_err(|_| FenError::InvalidNumber(fullmove_part.to_string()))?;
+        pos.fullmove_number = fullmove_part.parse().map_err(|_| FenError::InvalidNumber {
+            field: FenField::FullmoveNumber,
+            value: fullmove_part.to_string(),
+        })?;
 
+        pos.refresh_hash();
         Ok(pos)
     }
     
-    pub fn to_string(pos: &Position) -> String {
+    pub fn to_string<B: BB>(pos: &Position<B>) -> Result<String, FenError> {
+        Self::to_string_with(pos, &PieceRegistry::standard())
+    }
+
+    /// Write `pos` with the piece symbols in `registry`, which has to name every
+    /// piece on the board and in the pockets
+    pub fn to_string_with<B: BB>(pos: &Position<B>, registry: &PieceRegistry<B>) -> Result<String, FenError> {
         let mut fen = String::new();
+        let wall = WALL_SYMBOL.to_string();
         
         for rank in (0..pos.dims.height).rev() {
             if rank < pos.dims.height - 1 {
@@ -182,17 +272,21 @@ impl Fen {
             for file in 0..pos.dims.width {
                 let sq = Square::from_rank_file(rank, file, &pos.dims);
                 
-                match pos.piece_at(sq) {
-                    Some(piece) => {
-                        if empty_count > 0 {
-                            fen.push_str(&empty_count.to_string());
-                            empty_count = 0;
-                        }
-                        fen.push(piece_to_symbol(piece));
-                    }
+                let symbol = match pos.piece_at(sq) {
+                    Some(piece) => registry.symbol(piece).ok_or(FenError::UnknownPiece(piece))?,
+                    None if pos.is_wall(sq) => &wall,
                     None => {
                         empty_count += 1;
+                        continue;
                     }
+                };
+                if empty_count > 0 {
+                    fen.push_str(&empty_count.to_string());
+                    empty_count = 0;
+                }
+                fen.push_str(symbol);
+                if pos.holdings && pos.promoted.contains(sq) {
+                    fen.push(PROMOTED_SUFFIX);
                 }
             }
             
@@ -201,6 +295,20 @@ impl Fen {
             }
         }
         
+        if pos.holdings {
+            fen.push('[');
+            for color in [Color::White, Color::Black] {
+                for (kind, count) in pos.pocket(color).iter() {
+                    let piece = Piece { color, kind };
+                    let symbol = registry.symbol(piece).ok_or(FenError::UnknownPiece(piece))?;
+                    for _ in 0..count {
+                        fen.push_str(symbol);
+                    }
+                }
+            }
+            fen.push(']');
+        }
+        
         fen.push(' ');
         fen.push(match pos.side_to_move {
             Color::White => 'w',
@@ -229,63 +337,28 @@ impl Fen {
         
         fen.push(' ');
         match pos.ep_square {
-            Some(sq) => {
-                let (file, rank) = sq.file_rank(&pos.dims);
-                fen.push((b'a' + file) as char);
-                fen.push((b'1' + rank) as char);
-            }
+            Some(sq) => fen.push_str(&notation::format_square(sq, &pos.dims)),
             None => fen.push('-'),
         }
         
         fen.push_str(&format!(" {} {}", pos.halfmove_clock, pos.fullmove_number));
         
-        fen
-    }
-}
-
-fn piece_to_symbol(piece: Piece) -> char {
-    match (piece.color, piece.kind) {
-        (Color::White, PieceKind::Pawn) => 'P',
-        (Color::White, PieceKind::Knight) => 'N',
-        (Color::White, PieceKind::Bishop) => 'B',
-        (Color::White, PieceKind::Rook) => 'R',
-        (Color::White, PieceKind::Queen) => 'Q',
-        (Color::White, PieceKind::King) => 'K',
-        (Color::Black, PieceKind::Pawn) => 'p',
-        (Color::Black, PieceKind::Knight) => 'n',
-        (Color::Black, PieceKind::Bishop) => 'b',
-        (Color::Black, PieceKind::Rook) => 'r',
-        (Color::Black, PieceKind::Queen) => 'q',
-        (Color::Black, PieceKind::King) => 'k',
-        (Color::White, PieceKind::Custom(id)) => {
-            if id < 26 {
-                (b'A' + id) as char
-            } else {
-                '?'
-            }
-        }
-        (Color::Black, PieceKind::Custom(id)) => {
-            if id < 26 {
-                (b'a' + id) as char
-            } else {
-                '?'
-            }
-        }
+        Ok(fen)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::board::{Dimensions, BB};
-    use crate::piece::{Color, PieceKind};
+    use crate::board::{BitBoard256, BitBoard64, Dimensions, BB};
+    use crate::piece::{Color, PieceDef, PieceKind};
 
     #[test]
     fn fen_parse_starting_position() {
         let dims = Dimensions::standard();
         let fen_str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         assert!(pos.piece_bb(Color::White, PieceKind::King).count() == 1);
         assert!(pos.piece_bb(Color::Black, PieceKind::Pawn).count() == 8);
@@ -307,8 +380,8 @@ mod tests {
         let dims = Dimensions::standard();
         let fen_str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
-        let fen_output = Fen::to_string(&pos);
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
+        let fen_output = Fen::to_string(&pos).unwrap();
         
         assert!(fen_output.contains("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"));
         assert!(fen_output.contains("w"));
@@ -319,7 +392,7 @@ mod tests {
         let dims = Dimensions::standard();
         let fen_str = "8/8/8/8/8/8/8/8 w - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         assert_eq!(pos.all.count(), 0);
         assert_eq!(pos.side_to_move, Color::White);
@@ -330,7 +403,7 @@ mod tests {
         let dims = Dimensions::standard();
         let fen_str = "8/8/8/8/8/8/8/8 b - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         assert_eq!(pos.side_to_move, Color::Black);
     }
@@ -339,12 +412,12 @@ mod tests {
     fn fen_parse_en_passant() {
         let dims = Dimensions::standard();
         let fen_str = "4k3/6p1/8/pP1pP3/7P/8/8/4K3 w - d6 0 6";
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         assert!(pos.ep_square.is_some());
         let ep_sq = pos.ep_square.unwrap();
         let (file, rank) = ep_sq.file_rank(&dims);
-        assert_eq!(file, 4);
-        assert_eq!(rank, 2);
+        assert_eq!(file, 3);
+        assert_eq!(rank, 5);
     }
     
     #[test]
@@ -352,7 +425,7 @@ mod tests {
         let dims = Dimensions::standard();
         let fen_str = "r3kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQK2R w Kq - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         
         assert!(pos.castling_rights.has_white_kingside());
         assert!(!pos.castling_rights.has_white_queenside());
@@ -365,24 +438,52 @@ mod tests {
         let dims = Dimensions::new(12, 8);
         let fen_str = "12/12/12/12/12/12/12/12 w - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard256> = Fen::parse(fen_str, dims).unwrap();
         assert_eq!(pos.all.count(), 0);
     }
     
     #[test]
     fn fen_parse_custom_pieces() {
         let dims = Dimensions::standard();
-        let fen_str = "8/8/8/8/3Xx3/8/8/8 w - - 0 1";        
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let fen_str = "8/8/8/8/3Xx3/8/8/8 w - - 0 1";
+        assert_eq!(
+            Fen::parse::<BitBoard64>(fen_str, dims).unwrap_err(),
+            FenError::UnknownPieceSymbol { row: 4, col: 3, symbol: 'X' },
+        );
+
+        let mut registry = PieceRegistry::standard();
+        registry.register(PieceDef::new(PieceKind::Custom(23), "xiangqi", "X")).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse_with(fen_str, dims, &registry).unwrap();
         assert_eq!(pos.all.count(), 2);
+        assert_eq!(pos.piece_bb(Color::White, PieceKind::Custom(23)).count(), 1);
+        assert_eq!(pos.piece_bb(Color::Black, PieceKind::Custom(23)).count(), 1);
+        assert_eq!(Fen::to_string_with(&pos, &registry).unwrap(), fen_str);
+        let err = Fen::to_string(&pos).unwrap_err();
+        assert_eq!(err, FenError::UnknownPiece(Piece { color: Color::White, kind: PieceKind::Custom(23) }));
+        assert_eq!(err.to_string(), "no FEN symbol for White Custom(23)");
     }
-    
+
+    #[test]
+    fn fen_parse_multi_character_symbols() {
+        let dims = Dimensions::new(9, 9);
+        let mut registry = PieceRegistry::standard();
+        registry.register(PieceDef::new(PieceKind::Custom(0), "tokin", "+P")).unwrap();
+        registry.register(PieceDef::new(PieceKind::Custom(1), "cannon", "Ca")).unwrap();
+
+        let fen_str = "4k4/9/1Ca2+p4/9/9/9/9/+P8/4K4 w - - 0 1";
+        let pos: Position<BitBoard256> = Fen::parse_with(fen_str, dims, &registry).unwrap();
+        assert_eq!(pos.piece_bb(Color::Black, PieceKind::Custom(0)).count(), 1);
+        assert_eq!(pos.piece_bb(Color::White, PieceKind::Custom(0)).count(), 1);
+        assert_eq!(pos.piece_bb(Color::White, PieceKind::Custom(1)).count(), 1);
+        assert_eq!(Fen::to_string_with(&pos, &registry).unwrap(), fen_str);
+    }
+
     #[test]
     fn fen_parse_12x10_board() {
         let dims = Dimensions::new(12, 10);
         let fen_str = "12/12/12/12/12/12/12/12/12/12 w - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard256> = Fen::parse(fen_str, dims).unwrap();
         assert_eq!(pos.all.count(), 0);
         assert_eq!(pos.dims.width, 12);
         assert_eq!(pos.dims.height, 10);
@@ -393,7 +494,7 @@ mod tests {
         let dims = Dimensions::new(7, 9);
         let fen_str = "7/7/7/7/7/7/7/7/7 w - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         assert_eq!(pos.dims.width, 7);
         assert_eq!(pos.dims.height, 9);
     }
@@ -403,7 +504,7 @@ mod tests {
         let dims = Dimensions::new(5, 5);
         let fen_str = "5/5/5/5/5 w - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         assert_eq!(pos.dims.width, 5);
         assert_eq!(pos.dims.height, 5);
     }
@@ -413,7 +514,7 @@ mod tests {
         let dims = Dimensions::new(10, 10);
         let fen_str = "10/10/10/10/10/10/10/10/10/10 w - - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard256> = Fen::parse(fen_str, dims).unwrap();
         assert_eq!(pos.dims.width, 10);
         assert_eq!(pos.dims.height, 10);
     }
@@ -423,7 +524,7 @@ mod tests {
         let dims = Dimensions::standard();
         let fen_str = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
         
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         assert!(pos.piece_bb(Color::White, PieceKind::Rook).count() == 2);
         assert!(pos.piece_bb(Color::Black, PieceKind::Rook).count() == 2);
         assert!(pos.castling_rights.has_white_kingside());
@@ -435,7 +536,90 @@ mod tests {
         let dims = Dimensions::standard();
         let fen_str = "2r3k1/8/8/8/8/8/8/8 w - - 0 1";
 
-        let pos = Fen::parse(fen_str, dims).unwrap();
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
         assert_eq!(pos.all.count(), 2);
     }
+    
+    #[test]
+    fn fen_walls_round_trip() {
+        let dims = Dimensions::new(10, 10);
+        let fen_str = "rnbqkbbnqr/pppppppppp/10/3..5/10/10/5..3/10/PPPPPPPPPP/RNBQKBBNQR w - - 0 1";
+        
+        let pos: Position<BitBoard256> = Fen::parse(fen_str, dims).unwrap();
+        assert_eq!(pos.walls.count(), 4);
+        assert!(pos.is_wall(Square::from_rank_file(6, 3, &dims)));
+        assert!(pos.is_wall(Square::from_rank_file(3, 6, &dims)));
+        assert_eq!(pos.all.count(), 40);
+        assert_eq!(Fen::to_string(&pos).unwrap(), fen_str);
+    }
+    
+    #[test]
+    fn fen_holdings_round_trip() {
+        let dims = Dimensions::standard();
+        let fen_str = "r1b1k2r/ppp2ppp/2n5/3Q~4/8/8/PPP2PPP/R1B1K2R[PNNbq] w KQkq - 0 9";
+        let pos: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
+        
+        assert!(pos.holdings);
+        assert_eq!(pos.pocket(Color::White).count(PieceKind::Knight), 2);
+        assert_eq!(pos.pocket(Color::Black).count(PieceKind::Queen), 1);
+        assert!(pos.promoted.contains(Square::from_rank_file(4, 3, &dims)));
+        assert_eq!(Fen::to_string(&pos).unwrap(), fen_str);
+        
+        let plain: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/8/4K3[] w - - 0 1", dims).unwrap();
+        assert!(plain.holdings && plain.pocket(Color::White).is_empty());
+        assert_ne!(plain.hash, Fen::parse::<BitBoard64>("4k3/8/8/8/8/8/8/4K3[P] w - - 0 1", dims).unwrap().hash);
+        
+        let parse = |fen: &str| Fen::parse::<BitBoard64>(fen, dims).unwrap_err();
+        assert_eq!(parse("4k3/8/8/8/8/8/8/4K3[Nx] w - - 0 1"), FenError::InvalidHoldings("Nx".to_string()));
+        assert_eq!(parse("4k3/8/8/8/8/8/8/4K3[N w - - 0 1"), FenError::InvalidHoldings("N".to_string()));
+        let crowded = "P".repeat(256);
+        assert_eq!(
+            parse(&format!("4k3/8/8/8/8/8/8/4K3[{crowded}] w - - 0 1")),
+            FenError::InvalidHoldings(crowded),
+        );
+    }
+    
+    #[test]
+    fn fen_errors_point_at_the_problem() {
+        let dims = Dimensions::standard();
+        let parse = |fen: &str| Fen::parse::<BitBoard64>(fen, dims).unwrap_err();
+        
+        assert_eq!(
+            parse("rnbqkbnr/pppp#ppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            FenError::UnknownPieceSymbol { row: 1, col: 4, symbol: '#' },
+        );
+        assert_eq!(
+            parse("8/8/8/8/8/8/9/8 w - - 0 1"),
+            FenError::ColCountMismatch { row: 6, expected: 8, got: 9 },
+        );
+        assert_eq!(
+            parse("8/8/8/8/8/8/8/7R65535 w - - 0 1"),
+            FenError::ColCountMismatch { row: 7, expected: 8, got: u16::MAX },
+        );
+        assert_eq!(parse("8/8/8/8/8/8/8/8 w KX - 0 1"), FenError::InvalidCastling('X'));
+        assert_eq!(parse("8/8/8/8/8/8/8/8 x - - 0 1"), FenError::InvalidActiveColor("x".to_string()));
+        assert_eq!(parse("8/8/8/8/8/8/8/8 w - e9 0 1"), FenError::InvalidEnPassant("e9".to_string()));
+        assert_eq!(
+            parse("8/8/8/8/8/8/8/8 w - - x 1"),
+            FenError::InvalidNumber { field: FenField::HalfmoveClock, value: "x".to_string() },
+        );
+        assert_eq!(parse("8/8/8/8/8/8/8/8 w"), FenError::MissingFields { got: 2 });
+        assert_eq!(
+            Fen::parse::<BitBoard64>("10/10/10/10/10/10/10/10/10/10 w - - 0 1", Dimensions::new(10, 10)).unwrap_err(),
+            FenError::Dimensions(DimensionsError::TooManySquares { squares: 100, max: 64 }),
+        );
+        
+        let message = parse("rnbqkbnr/pppp#ppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").to_string();
+        assert_eq!(message, "unknown piece symbol '#' in FEN row 2, column 5");
+    }
+    
+    #[test]
+    fn fen_parse_en_passant_on_high_rank() {
+        let dims = Dimensions::new(12, 12);
+        let fen_str = "12/12/12/12/12/12/12/12/12/12/12/12 b - c11 0 1";
+        
+        let pos: Position<BitBoard256> = Fen::parse(fen_str, dims).unwrap();
+        assert_eq!(pos.ep_square, Some(Square::from_rank_file(10, 2, &dims)));
+        assert_eq!(Fen::to_string(&pos).unwrap(), fen_str);
+    }
 }
@@ -1,6 +1,17 @@
+#[allow(clippy::module_inception)]
 pub mod position;
 pub mod fen;
+pub mod zobrist;
+pub mod any;
+pub mod pocket;
+pub mod validate;
+pub mod outcome;
 
-pub use position::Position;
-pub use fen::{Fen, FenError};
+pub use position::{Position, CastlingRights, StateSnapshot};
+pub use fen::{Fen, FenError, FenField};
+pub use zobrist::{Zobrist, ZobristKey};
+pub use any::AnyPosition;
+pub use pocket::Pocket;
+pub use validate::PositionProblem;
+pub use outcome::{GameOutcome, WinReason, DrawReason, FIFTY_MOVE_HALFMOVES};
 
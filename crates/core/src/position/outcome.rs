@@ -0,0 +1,174 @@
+use crate::{
+    board::{Square, BB},
+    movegen::MoveGenerator,
+    piece::{Color, PieceKind},
+    position::Position,
+};
+
+/// Number of halfmoves without a capture or pawn move that ends the game
+pub const FIFTY_MOVE_HALFMOVES: u16 = 100;
+
+/// How a finished game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Win { winner: Color, reason: WinReason },
+    Draw(DrawReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinReason {
+    Checkmate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+impl<B: BB> Position<B> {
+    /// The result of the game if it is over, checking mate and stalemate first so a
+    /// mate delivered on the hundredth halfmove still wins
+    pub fn outcome(&self, gen: &MoveGenerator<B>) -> Option<GameOutcome> {
+        if gen.generate_legal(self).is_empty() {
+            return Some(if gen.in_check(self) {
+                GameOutcome::Win { winner: self.side_to_move.opposite(), reason: WinReason::Checkmate }
+            } else {
+                GameOutcome::Draw(DrawReason::Stalemate)
+            });
+        }
+        if self.repetition_count() >= 3 {
+            return Some(GameOutcome::Draw(DrawReason::ThreefoldRepetition));
+        }
+        if self.halfmove_clock >= FIFTY_MOVE_HALFMOVES {
+            return Some(GameOutcome::Draw(DrawReason::FiftyMoveRule));
+        }
+        if self.has_insufficient_material() {
+            return Some(GameOutcome::Draw(DrawReason::InsufficientMaterial));
+        }
+        None
+    }
+
+    /// How many times the current position has occurred, counting itself. Only the
+    /// moves since the last capture or pawn move are searched, as nothing before an
+    /// irreversible move can repeat, and positions set up by FEN have no history.
+    pub fn repetition_count(&self) -> usize {
+        let reversible = (self.halfmove_clock as usize).min(self.history.len());
+        let recent = &self.history[self.history.len() - reversible..];
+        // the snapshot `k` entries from the end holds the key from `k` plies ago, and
+        // only even distances have the same side to move
+        1 + recent.iter().rev().skip(1).step_by(2).filter(|snapshot| snapshot.hash == self.hash).count()
+    }
+
+    /// Neither side can ever checkmate: only kings remain, or one knight or bishop
+    /// among them, or only bishops that all stand on squares of one color. Pawns,
+    /// rooks, queens, custom pieces and anything in a pocket count as mating material,
+    /// since whether a custom piece can force mate depends on its pattern.
+    pub fn has_insufficient_material(&self) -> bool {
+        if !self.pocket(Color::White).is_empty() || !self.pocket(Color::Black).is_empty() {
+            return false;
+        }
+        let minors = [PieceKind::King, PieceKind::Knight, PieceKind::Bishop];
+        if self.kinds().iter().any(|kind| !minors.contains(kind) && !self.kind_bb(*kind).is_empty()) {
+            return false;
+        }
+
+        let knights = self.kind_bb(PieceKind::Knight).count();
+        let mut bishops = self.kind_bb(PieceKind::Bishop);
+        if knights + bishops.count() <= 1 {
+            return true;
+        }
+        if knights > 0 {
+            return false;
+        }
+        let shade = |sq: Square| {
+            let (file, rank) = sq.file_rank(&self.dims);
+            (file + rank) % 2
+        };
+        let first = bishops.pop_lsb().map(shade);
+        std::iter::from_fn(|| bishops.pop_lsb()).all(|sq| Some(shade(sq)) == first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64, Dimensions};
+    use crate::moves::Move;
+    use crate::position::Fen;
+
+    fn outcome(fen: &str, dims: Dimensions) -> Option<GameOutcome> {
+        let pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+        pos.outcome(&MoveGenerator::new(dims))
+    }
+
+    #[test]
+    fn outcome_mate_stalemate_and_fifty_moves() {
+        let dims = Dimensions::standard();
+        assert_eq!(outcome("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims), None);
+        assert_eq!(
+            outcome("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", dims),
+            Some(GameOutcome::Win { winner: Color::Black, reason: WinReason::Checkmate }),
+        );
+        assert_eq!(outcome("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", dims), Some(GameOutcome::Draw(DrawReason::Stalemate)));
+        assert_eq!(
+            outcome("4k3/8/8/8/8/8/8/R3K3 b - - 100 80", dims),
+            Some(GameOutcome::Draw(DrawReason::FiftyMoveRule)),
+        );
+        assert_eq!(outcome("4k3/8/8/8/8/8/8/R3K3 b - - 99 80", dims), None);
+        // mate on the last move of the fifty still counts
+        assert_eq!(
+            outcome("R3k3/8/4K3/8/8/8/8/8 b - - 100 80", dims),
+            Some(GameOutcome::Win { winner: Color::White, reason: WinReason::Checkmate }),
+        );
+    }
+
+    #[test]
+    fn outcome_threefold_repetition() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> =
+            Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        let mut last = None;
+        for round in 1..=2 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                assert_eq!(pos.outcome(&gen), None);
+                let mv = Move::from_uci(uci, &pos).unwrap();
+                pos.make_move(mv);
+                last = Some(mv);
+            }
+            assert_eq!(pos.repetition_count(), round + 1);
+        }
+        assert_eq!(pos.outcome(&gen), Some(GameOutcome::Draw(DrawReason::ThreefoldRepetition)));
+
+        pos.unmake_move(last.unwrap());
+        assert_eq!(pos.repetition_count(), 2);
+    }
+
+    #[test]
+    fn outcome_insufficient_material() {
+        let insufficient = |fen: &str, dims: Dimensions| {
+            let pos: Position<BitBoard256> = Fen::parse(fen, dims).unwrap();
+            pos.has_insufficient_material()
+        };
+        let dims = Dimensions::standard();
+        assert!(insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1", dims));
+        assert!(insufficient("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", dims));
+        assert!(insufficient("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1", dims));
+        assert!(!insufficient("1b2k3/8/8/8/8/8/8/4KB2 w - - 0 1", dims));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", dims));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/4K2R w - - 0 1", dims));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/4K3[n] w - - 0 1", dims));
+
+        let dims = Dimensions::new(10, 10);
+        assert!(!insufficient("5k4/10/10/10/10/10/10/10/10/2B1KB4 w - - 0 1", dims));
+        assert!(insufficient("5k4/10/10/10/10/10/10/10/10/2B1K1B3 w - - 0 1", dims));
+
+        assert_eq!(
+            outcome("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", Dimensions::standard()),
+            Some(GameOutcome::Draw(DrawReason::InsufficientMaterial)),
+        );
+    }
+}
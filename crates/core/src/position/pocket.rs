@@ -0,0 +1,50 @@
+use crate::piece::PieceKind;
+
+/// Pieces in hand for one side, counted per kind
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pocket {
+    /// Indexed by `PieceKind::index`
+    counts: Vec<u8>,
+}
+
+impl Pocket {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn count(&self, kind: PieceKind) -> u8 {
+        self.counts.get(kind.index()).copied().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&count| count == 0)
+    }
+
+    /// Kinds in hand with their counts, in `PieceKind::index` order
+    pub fn iter(&self) -> impl Iterator<Item = (PieceKind, u8)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(idx, &count)| (PieceKind::from_index(idx), count))
+    }
+
+    /// Add one piece and return the new count, or `None` if the pocket already holds
+    /// `u8::MAX` of the kind
+    pub(crate) fn add(&mut self, kind: PieceKind) -> Option<u8> {
+        let idx = kind.index();
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, 0);
+        }
+        self.counts[idx] = self.counts[idx].checked_add(1)?;
+        Some(self.counts[idx])
+    }
+
+    /// Take one piece and return the new count, or `None` if there was none
+    pub(crate) fn remove(&mut self, kind: PieceKind) -> Option<u8> {
+        let count = self.counts.get_mut(kind.index()).filter(|count| **count > 0)?;
+        *count -= 1;
+        Some(*count)
+    }
+}
@@ -2,11 +2,13 @@ use crate::{
     board::{Dimensions, Square, BitBoard, BB},
     piece::{PieceKind, Color, Piece},
     moves::*,
+    movegen::standard::StandardPatterns,
+    position::{pocket::Pocket, zobrist::{Zobrist, ZobristKey}},
+    error::{DimensionsError, MoveError},
 };
-use std::collections::HashMap;
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct CastlingRights(u8);
 
 impl CastlingRights {
@@ -75,126 +77,241 @@ impl CastlingRights {
 #[derive(Debug,Clone)]
 pub struct StateSnapshot {
     pub captured: Option<Piece>,
+    /// The captured piece had been promoted, see `Position::promoted`
+    pub captured_promoted: bool,
     pub castling_rights: CastlingRights,
     pub ep_square: Option<Square>,
     pub halfmove_clock: u16,
     pub fullmove_number: u16,
+    pub hash: ZobristKey,
+    /// Duck square before the move
+    pub duck: Option<Square>,
 }
 
 
 #[derive(Debug, Clone)]
-pub struct Position {
+pub struct Position<B = BitBoard> {
     pub dims: Dimensions,
     pub side_to_move: Color,
-        pub pieces: HashMap<PieceKind, BitBoard>,
+    
+    /// Per-kind bitboards indexed by `PieceKind::index`, grown when a new custom kind appears
+    pieces: Vec<B>,
+    
+    /// Kinds that have appeared on the board, ordered by index
+    kinds: Vec<PieceKind>,
+    
+    /// Piece on each square
+    mailbox: Vec<Option<Piece>>,
     
     /// Occupancy bitboards [white, black]
-    pub occ: [BitBoard; 2],
+    pub occ: [B; 2],
+    
+    pub all: B,
+    
+    /// Non-playable squares. Walls hold no piece, block sliders and are never move targets.
+    pub walls: B,
+    
+    /// The duck, a wall that moves: placed with `Move::with_duck` and also set in `walls`
+    pub duck: Option<Square>,
+    
+    /// Pieces in hand [white, black], placed with drop moves and gating
+    pockets: [Pocket; 2],
+    
+    /// Captured pieces go to the capturer's pocket, as in Crazyhouse
+    pub holdings: bool,
+    
+    /// Pieces that reached their kind by promotion. Only tracked with `holdings`, where
+    /// they return to the pocket as pawns when captured.
+    pub promoted: B,
     
-    pub all: BitBoard,
     pub castling_rights: CastlingRights,
+    
+    /// File each king castles from [white, black]. The e-file on boards of eight or more
+    /// files and the middle file on narrower ones, unless `detect_castling_king_files`
+    /// finds the kings elsewhere, as `Fen::parse` does.
+    pub castling_king_files: [u8; 2],
+    
     pub ep_square: Option<Square>,
     pub halfmove_clock: u16,
     pub fullmove_number: u16,
     pub history: Vec<StateSnapshot>,
+    
+    /// Zobrist key, kept up to date by `set_piece`, `remove_piece`, `switch_side`
+    /// and `make_move`. Call `refresh_hash` after writing the state fields directly.
+    pub hash: ZobristKey,
 
 }
 
-impl Position {
+impl<B: BB> Position<B> {
+    /// # Panics
+    /// If `dims` is out of range or does not fit in `B`; see `try_new_empty`.
     pub fn new_empty(dims: Dimensions) -> Self {
-        Self {
+        Self::try_new_empty(dims).unwrap_or_else(|err| panic!("{err}"))
+    }
+    
+    pub fn try_new_empty(dims: Dimensions) -> Result<Self, DimensionsError> {
+        dims.validate()?;
+        if dims.num_squares() > B::MAX_SQUARES {
+            return Err(DimensionsError::TooManySquares {
+                squares: dims.num_squares(),
+                max: B::MAX_SQUARES,
+            });
+        }
+        Ok(Self {
             dims,
             side_to_move: Color::White,
-            pieces: HashMap::new(),
-            occ: [BitBoard::empty_for_dims(&dims), BitBoard::empty_for_dims(&dims)],
-            all: BitBoard::empty_for_dims(&dims),
+            pieces: vec![B::empty_for_dims(&dims); PieceKind::STANDARD_COUNT],
+            kinds: Vec::new(),
+            mailbox: vec![None; dims.num_squares() as usize],
+            occ: [B::empty_for_dims(&dims), B::empty_for_dims(&dims)],
+            all: B::empty_for_dims(&dims),
+            walls: B::empty_for_dims(&dims),
+            duck: None,
+            pockets: [Pocket::new(), Pocket::new()],
+            holdings: false,
+            promoted: B::empty_for_dims(&dims),
             castling_rights: CastlingRights::new(),
+            castling_king_files: [Self::default_castling_king_file(&dims); 2],
             ep_square: None,
             halfmove_clock: 0,
             fullmove_number: 1,
             history: vec![],
-        }
+            hash: 0,
+        })
+    }
+    
+    /// Recompute the Zobrist key from scratch
+    pub fn refresh_hash(&mut self) {
+        self.hash = Zobrist::compute(self);
     }
     
     pub fn set_piece(&mut self, sq: Square, piece: Piece) {
-        self.remove_piece(sq);
-        
-        let bb = self.pieces.entry(piece.kind).or_insert_with(|| BitBoard::empty_for_dims(&self.dims));
-        *bb = bb.set(sq);
+        debug_assert!(!self.is_wall(sq), "cannot place a piece on a wall");
+        let ep_key = Zobrist::en_passant(self.capturable_ep_square());
+        self.take_piece(sq);
+        self.put_piece(sq, piece);
+        self.hash ^= ep_key ^ Zobrist::en_passant(self.capturable_ep_square());
+    }
+
+    pub fn remove_piece(&mut self, sq: Square) -> Option<Piece> {
+        let ep_key = Zobrist::en_passant(self.capturable_ep_square());
+        let piece = self.take_piece(sq)?;
+        self.hash ^= ep_key ^ Zobrist::en_passant(self.capturable_ep_square());
+        Some(piece)
+    }
+
+    /// Place `piece` on the empty square `sq`. The en passant key is left to the
+    /// caller, since pawns next to the en passant square decide whether it is hashed.
+    fn put_piece(&mut self, sq: Square, piece: Piece) {
+        let kind_idx = piece.kind.index();
+        if kind_idx >= self.pieces.len() {
+            self.pieces.resize(kind_idx + 1, B::empty_for_dims(&self.dims));
+        }
+        if let Err(at) = self.kinds.binary_search_by_key(&kind_idx, |kind| kind.index()) {
+            self.kinds.insert(at, piece.kind);
+        }
+        self.pieces[kind_idx] = self.pieces[kind_idx].set(sq);
+        self.mailbox[sq.idx() as usize] = Some(piece);
 
         let idx = piece.color as usize;
         self.occ[idx] = self.occ[idx].set(sq);
 
         self.all = self.all.set(sq);
+        self.hash ^= Zobrist::piece(piece, sq);
     }
 
-    pub fn remove_piece(&mut self, sq: Square) -> Option<Piece> {
-        if !self.all.contains(sq) {
-            return None;
-        }
+    /// Counterpart of `put_piece`
+    fn take_piece(&mut self, sq: Square) -> Option<Piece> {
+        let piece = self.mailbox[sq.idx() as usize].take()?;
         
-        let mut found_kind = None;
-        for (&kind, bb) in self.pieces.iter_mut() {
-            if bb.contains(sq) {
-                *bb = bb.clear(sq);
-                found_kind = Some(kind);
-                break;
-            }
-        }
+        let kind_idx = piece.kind.index();
+        self.pieces[kind_idx] = self.pieces[kind_idx].clear(sq);
         
-        if let Some(kind) = found_kind {
-            let color = if self.occ[Color::White as usize].contains(sq) {
-                Color::White
-            } else {
-                Color::Black
-            };
-            
-            self.occ[color as usize] = self.occ[color as usize].clear(sq);
-            
-            // Remove from all occupancy
-            self.all = self.all.clear(sq);
-            
-            Some(Piece { color, kind })
-        } else {
-            None
-        }
+        let color_idx = piece.color as usize;
+        self.occ[color_idx] = self.occ[color_idx].clear(sq);
+        
+        self.all = self.all.clear(sq);
+        self.hash ^= Zobrist::piece(piece, sq);
+        Some(piece)
     }
 
+    #[inline]
     pub fn piece_at(&self, sq: Square) -> Option<Piece> {
-        if !self.all.contains(sq) {
-            return None;
-        }
-        
-        let color = if self.occ[Color::White as usize].contains(sq) {
-            Color::White
-        } else {
-            Color::Black
-        };
-
-        for (&kind, bb) in self.pieces.iter() {
-            if bb.contains(sq) {
-                return Some(Piece { color, kind });
-            }
-        }
-        None
+        self.mailbox[sq.idx() as usize]
+    }
+    
+    pub fn piece_bb(&self, color: Color, kind: PieceKind) -> B {
+        self.occ[color as usize].intersect(self.kind_bb(kind))
     }
     
-    pub fn piece_bb(&self, color: Color, kind: PieceKind) -> BitBoard {
-        let color_bb = self.occ[color as usize];
-        let kind_bb = self.pieces.get(&kind).copied().unwrap_or_else(|| BitBoard::empty_for_dims(&self.dims));
-        color_bb.intersect(kind_bb)
+    /// Kinds that have been placed on the board, in `PieceKind::index` order
+    pub fn kinds(&self) -> &[PieceKind] {
+        &self.kinds
     }
     
-    pub fn color_bb(&self, color: Color) -> BitBoard {
+    pub fn color_bb(&self, color: Color) -> B {
         self.occ[color as usize]
     }
     
-    pub fn kind_bb(&self, kind: PieceKind) -> BitBoard {
-        self.pieces.get(&kind).copied().unwrap_or_else(|| BitBoard::empty_for_dims(&self.dims))
+    pub fn kind_bb(&self, kind: PieceKind) -> B {
+        match self.pieces.get(kind.index()) {
+            Some(&bb) => bb,
+            None => B::empty_for_dims(&self.dims),
+        }
     }
     
     pub fn switch_side(&mut self) {
+        let ep_key = Zobrist::en_passant(self.capturable_ep_square());
         self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= Zobrist::side_to_move() ^ ep_key ^ Zobrist::en_passant(self.capturable_ep_square());
+    }
+    
+    /// Turn `sq` into a wall, removing any piece on it
+    pub fn set_wall(&mut self, sq: Square) {
+        self.remove_piece(sq);
+        if !self.walls.contains(sq) {
+            self.walls = self.walls.set(sq);
+            self.hash ^= Zobrist::wall(sq);
+        }
+    }
+    
+    pub fn clear_wall(&mut self, sq: Square) {
+        if self.walls.contains(sq) {
+            self.walls = self.walls.clear(sq);
+            self.hash ^= Zobrist::wall(sq);
+        }
+    }
+    
+    pub fn is_wall(&self, sq: Square) -> bool {
+        self.walls.contains(sq)
+    }
+    
+    pub fn pocket(&self, color: Color) -> &Pocket {
+        &self.pockets[color as usize]
+    }
+    
+    /// Put a piece into the pocket; false if it already holds `u8::MAX` of the kind
+    pub fn add_to_pocket(&mut self, color: Color, kind: PieceKind) -> bool {
+        let Some(count) = self.pockets[color as usize].add(kind) else {
+            return false;
+        };
+        self.hash ^= Zobrist::pocket(color, kind, count - 1) ^ Zobrist::pocket(color, kind, count);
+        true
+    }
+    
+    /// Take a piece out of the pocket; false if there was none
+    pub fn remove_from_pocket(&mut self, color: Color, kind: PieceKind) -> bool {
+        let Some(count) = self.pockets[color as usize].remove(kind) else {
+            return false;
+        };
+        self.hash ^= Zobrist::pocket(color, kind, count + 1) ^ Zobrist::pocket(color, kind, count);
+        true
+    }
+    
+    /// Squares that stop sliders: every piece plus the walls
+    #[inline]
+    pub fn blockers(&self) -> B {
+        self.all.union(self.walls)
     }
     
     pub fn is_occupied(&self, sq: Square) -> bool {
@@ -205,90 +322,344 @@ impl Position {
         self.occ[color as usize].contains(sq)
     }
 
-    pub fn make_move(&mut self,mv: Move){
+    /// Apply `mv` after checking that it is well formed for this position: the mover
+    /// owns the source piece, the target is free of own pieces and walls, the move type
+    /// matches the board and standard pieces follow their pattern; castling needs its
+    /// right and a clear path. King safety and custom patterns are not checked; moves
+    /// taken from `MoveGenerator::generate_legal` are always accepted.
+    pub fn try_make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.make_move(mv);
+        Ok(())
+    }
+    
+    /// Take back `mv`, which has to be the last move made
+    pub fn try_unmake_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        if self.history.is_empty() {
+            return Err(MoveError::NothingToUndo);
+        }
+        let mover = self.side_to_move.opposite();
+        let dst = mv.dst();
+        let on_dst = self.mailbox.get(dst.idx() as usize).copied().flatten();
+        if on_dst.map(|piece| piece.color) != Some(mover) {
+            return Err(MoveError::NotLastMove { mv, dims: self.dims });
+        }
+        self.unmake_move(mv);
+        Ok(())
+    }
+    
+    pub(crate) fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        let src = mv.src();
+        let dst = mv.dst();
+        let extra = mv.duck_square().into_iter().chain(mv.gating().map(|(_, sq)| sq));
+        for sq in [src, dst].into_iter().chain(extra) {
+            if sq.idx() >= self.dims.num_squares() {
+                return Err(MoveError::OffBoard(sq));
+            }
+        }
+        self.check_primary_move(mv)?;
+        
+        // squares the primary move empties and fills; castling also moves the rook
+        let (rook_src, rook_dst) = match mv.kind() {
+            MoveType::Castling => {
+                let (rook_src, rook_dst) = self.castling_rook_squares(src, dst);
+                (Some(rook_src), Some(rook_dst))
+            }
+            _ => (None, None),
+        };
+        let vacant_after = |sq: Square| {
+            let vacated = sq == src || Some(sq) == rook_src;
+            sq != dst && Some(sq) != rook_dst && !self.is_wall(sq) && (vacated || !self.is_occupied(sq))
+        };
+        if let Some((kind, sq)) = mv.gating() {
+            if mv.kind() == MoveType::Drop || self.pocket(self.side_to_move).count(kind) == 0 {
+                return Err(MoveError::NotInPocket(kind));
+            }
+            if !vacant_after(sq) {
+                return Err(MoveError::BlockedTarget(sq.on(self.dims)));
+            }
+        }
+        if let Some(sq) = mv.duck_square() {
+            let on_gate = mv.gating().is_some_and(|(_, gate)| gate == sq);
+            if self.duck == Some(sq) || on_gate || !vacant_after(sq) {
+                return Err(MoveError::BlockedTarget(sq.on(self.dims)));
+            }
+        }
+        Ok(())
+    }
+    
+    fn check_primary_move(&self, mv: Move) -> Result<(), MoveError> {
+        let src = mv.src();
+        let dst = mv.dst();
+        if let Some(kind) = mv.drop_kind() {
+            if self.pocket(self.side_to_move).count(kind) == 0 {
+                return Err(MoveError::NotInPocket(kind));
+            }
+            if self.is_wall(dst) || self.is_occupied(dst) {
+                return Err(MoveError::BlockedTarget(dst.on(self.dims)));
+            }
+            return Ok(());
+        }
+        
+        let piece = self.piece_at(src).ok_or(MoveError::EmptySource(src.on(self.dims)))?;
+        if piece.color != self.side_to_move {
+            return Err(MoveError::NotSideToMove(src.on(self.dims)));
+        }
+        if src == dst || self.is_wall(dst) || self.is_occupied_by(dst, piece.color) {
+            return Err(MoveError::BlockedTarget(dst.on(self.dims)));
+        }
+        if self.holdings && mv.kind() != MoveType::Castling {
+            let capture_sq = if mv.kind() == MoveType::EnPassant { self.ep_capture_square(src, dst) } else { dst };
+            if let Some(victim) = self.piece_at(capture_sq) {
+                let kind = if self.promoted.contains(capture_sq) { PieceKind::Pawn } else { victim.kind };
+                if self.pocket(piece.color).count(kind) == u8::MAX {
+                    return Err(MoveError::PocketFull(kind));
+                }
+            }
+        }
+        
+        let enemy_on_dst = self.is_occupied_by(dst, piece.color.opposite());
+        let (src_file, src_rank) = src.file_rank(&self.dims);
+        let (dst_file, dst_rank) = dst.file_rank(&self.dims);
+        let promotion_rank = match piece.color {
+            Color::White => self.dims.height - 1,
+            Color::Black => 0,
+        };
+        let promotes = piece.kind == PieceKind::Pawn && dst_rank == promotion_rank;
+        let fits = match mv.kind() {
+            MoveType::Teleport => !enemy_on_dst,
+            MoveType::Quiet => !enemy_on_dst && !promotes && self.reaches(piece, src, dst),
+            MoveType::Capture => enemy_on_dst && !promotes && self.reaches(piece, src, dst),
+            MoveType::Promotion => promotes && self.reaches(piece, src, dst),
+            MoveType::EnPassant => {
+                let victim = Piece { color: piece.color.opposite(), kind: PieceKind::Pawn };
+                let friendly = self.color_bb(piece.color);
+                piece.kind == PieceKind::Pawn
+                    && self.ep_square == Some(dst)
+                    && StandardPatterns::pawn_attacks(src, piece.color, &self.dims, friendly).contains(dst)
+                    && self.piece_at(self.ep_capture_square(src, dst)) == Some(victim)
+            }
+            MoveType::Castling => {
+                let (rook_src, _) = self.castling_rook_squares(src, dst);
+                let (rook_file, _) = rook_src.file_rank(&self.dims);
+                let rook = Piece { color: piece.color, kind: PieceKind::Rook };
+                let right = match (piece.color, dst_file > src_file) {
+                    (Color::White, true) => self.castling_rights.has_white_kingside(),
+                    (Color::White, false) => self.castling_rights.has_white_queenside(),
+                    (Color::Black, true) => self.castling_rights.has_black_kingside(),
+                    (Color::Black, false) => self.castling_rights.has_black_queenside(),
+                };
+                // every square between king and rook, which holds both landing squares
+                let blockers = self.blockers();
+                let path_clear = (src_file.min(rook_file) + 1..src_file.max(rook_file))
+                    .all(|file| !blockers.contains(Square::from_rank_file(src_rank, file, &self.dims)));
+                piece.kind == PieceKind::King
+                    && right
+                    && src == self.castling_king_square(piece.color)
+                    && src_rank == dst_rank
+                    && src_file.abs_diff(dst_file) == 2
+                    && self.piece_at(rook_src) == Some(rook)
+                    && path_clear
+            }
+            MoveType::Drop => unreachable!("drops are checked above"),
+        };
+        if !fits {
+            return Err(MoveError::KindMismatch(mv.kind()));
+        }
+        Ok(())
+    }
+    
+    /// Whether `piece` on `src` can step to `dst` with its standard pattern. Custom
+    /// kinds move by the generator's registry, which the position does not hold, so
+    /// any target is accepted for them.
+    fn reaches(&self, piece: Piece, src: Square, dst: Square) -> bool {
+        let friendly = self.color_bb(piece.color);
+        let targets = match piece.kind {
+            PieceKind::Custom(_) => return true,
+            PieceKind::Pawn if self.is_occupied(dst) => {
+                StandardPatterns::pawn_attacks(src, piece.color, &self.dims, friendly)
+            }
+            PieceKind::Pawn => StandardPatterns::pawn_pushes(src, piece.color, &self.dims, self.blockers()),
+            kind => StandardPatterns::pattern_for::<B>(kind).attacks_from(src, piece.color, &self.dims, self.blockers(), friendly),
+        };
+        targets.contains(dst)
+    }
+    
+    /// # Panics
+    /// If there is no piece on the source square, a drop's piece is not in the pocket,
+    /// or a capture goes into a full pocket; see `try_make_move`.
+    pub fn make_move(&mut self, mv: Move) {
         let src = mv.src();
         let dst = mv.dst();
         let kind = mv.kind();
-        let moving_piece = self.piece_at(src).expect("No piece on src");
-        self.history.push(StateSnapshot { 
-            captured: self.piece_at(dst), 
-            castling_rights: self.castling_rights, 
-            ep_square: self.ep_square, 
-            halfmove_clock: self.halfmove_clock, 
-            fullmove_number: self.fullmove_number 
+        let moving_piece = match mv.drop_kind() {
+            Some(kind) => Piece { color: self.side_to_move, kind },
+            None => self.piece_at(src).expect("No piece on src"),
+        };
+        
+        let capture_sq = if kind == MoveType::EnPassant {
+            self.ep_capture_square(src, dst)
+        } else {
+            dst
+        };
+        let captured = match kind {
+            MoveType::Castling | MoveType::Drop => None,
+            _ => self.piece_at(capture_sq),
+        };
+        let captured_promoted = captured.is_some() && self.promoted.contains(capture_sq);
+        
+        self.history.push(StateSnapshot {
+            captured,
+            captured_promoted,
+            castling_rights: self.castling_rights,
+            ep_square: self.ep_square,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+            duck: self.duck,
         });
+        self.hash ^= Zobrist::castling(self.castling_rights) ^ Zobrist::en_passant(self.capturable_ep_square());
         self.ep_square = None;
-        match kind{
-            MoveType::Capture => {
-                self.remove_piece(src);
-                self.set_piece(dst, moving_piece);
-                self.halfmove_clock = 0;
-            }
-            MoveType::Quiet =>{
-                self.remove_piece(dst);
+        
+        match kind {
+            MoveType::Quiet | MoveType::Capture | MoveType::Teleport => {
                 self.remove_piece(src);
                 self.set_piece(dst, moving_piece);
-                self.halfmove_clock+=1;
             }
-            MoveType::Promotion =>{
-                let promo_kind = PieceKind::Queen;
+            MoveType::Promotion => {
+                let promo_kind = mv.promotion_kind().unwrap_or(PieceKind::Queen);
                 self.remove_piece(src);
                 self.set_piece(dst, Piece { color: moving_piece.color, kind: promo_kind });
-                self.halfmove_clock = 0;
             }
-            MoveType::EnPassant =>{
-                let ep_target = self.ep_square.expect("EP target missing");
-                self.remove_piece(ep_target);
+            MoveType::EnPassant => {
+                self.remove_piece(capture_sq);
                 self.remove_piece(src);
                 self.set_piece(dst, moving_piece);
-                self.halfmove_clock = 0;
             }
             MoveType::Castling => {
-                /*self.remove_piece(src);
+                let (rook_src, rook_dst) = self.castling_rook_squares(src, dst);
+                let rook = self.remove_piece(rook_src);
+                self.remove_piece(src);
                 self.set_piece(dst, moving_piece);
-                let (rook_src, rook_dst) = get_castling_rook_squares(src, dst);
-                let rook = self.piece_at(rook_src).unwrap();
-                self.remove_piece(rook_src);
-                self.set_piece(rook_dst, rook);*/
-
-                self.halfmove_clock += 1;
+                if let Some(rook) = rook {
+                    self.set_piece(rook_dst, rook);
+                }
+            }
+            MoveType::Drop => {
+                let in_pocket = self.remove_from_pocket(moving_piece.color, moving_piece.kind);
+                assert!(in_pocket, "No {:?} in pocket", moving_piece.kind);
+                self.set_piece(dst, moving_piece);
+            }
+        }
+        
+        if let Some((gated, gate_sq)) = mv.gating() {
+            let in_pocket = self.remove_from_pocket(moving_piece.color, gated);
+            assert!(in_pocket, "No {gated:?} in pocket");
+            self.set_piece(gate_sq, Piece { color: moving_piece.color, kind: gated });
+        }
+        if let Some(duck_sq) = mv.duck_square() {
+            if let Some(old) = self.duck {
+                self.clear_wall(old);
+            }
+            self.set_wall(duck_sq);
+            self.duck = Some(duck_sq);
+        }
+        
+        if self.holdings {
+            if let Some(victim) = captured {
+                let kind = if captured_promoted { PieceKind::Pawn } else { victim.kind };
+                assert!(self.add_to_pocket(moving_piece.color, kind), "Pocket full");
+            }
+            let was_promoted = self.promoted.contains(src) && kind != MoveType::Drop;
+            self.promoted = self.promoted.clear(src).clear(capture_sq);
+            if was_promoted || kind == MoveType::Promotion {
+                self.promoted = self.promoted.set(dst);
             }
         }
-        // handle ep creation for doule pawn push
-        // handle castle rights removal
+        
+        if moving_piece.kind == PieceKind::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        
+        if kind != MoveType::Drop {
+            self.update_castling_rights(src, dst, moving_piece);
+        }
         self.switch_side();
-
+        
+        // a double pawn push leaves the skipped square open to en passant; set after
+        // the side switch, which would otherwise take its key along
+        if moving_piece.kind == PieceKind::Pawn && kind != MoveType::Drop {
+            let (file, src_rank) = src.file_rank(&self.dims);
+            let (_, dst_rank) = dst.file_rank(&self.dims);
+            if src_rank.abs_diff(dst_rank) == 2 {
+                let ep_rank = (src_rank + dst_rank) / 2;
+                self.ep_square = Some(Square::from_rank_file(ep_rank, file, &self.dims));
+            }
+        }
+        self.hash ^= Zobrist::castling(self.castling_rights) ^ Zobrist::en_passant(self.capturable_ep_square());
+        
         if self.side_to_move == Color::White {
             self.fullmove_number += 1;
         }
-
     }
-
-    pub fn unmake_move(&mut self, mv: Move){
+    
+    /// # Panics
+    /// If the history is empty; see `try_unmake_move`.
+    pub fn unmake_move(&mut self, mv: Move) {
         let src = mv.src();
         let dst = mv.dst();
         let kind = mv.kind();
         let snapshot = self.history.pop().expect("No history");
         let captured = snapshot.captured;
         self.castling_rights = snapshot.castling_rights;
-        self.ep_square = snapshot.ep_square;
+        // restored along with the hash at the end, so the edits below need no en passant key
+        self.ep_square = None;
         self.halfmove_clock = snapshot.halfmove_clock;
         self.fullmove_number = snapshot.fullmove_number;
-
+        
         self.switch_side();
-        let moving_piece = self.remove_piece(dst).expect("Missiong destination");
-        match kind{
-            MoveType::Quiet => {
-                self.set_piece(src, moving_piece);
+        if let Some(duck_sq) = mv.duck_square() {
+            self.clear_wall(duck_sq);
+            if let Some(old) = snapshot.duck {
+                self.set_wall(old);
             }
-            MoveType::Capture => {
+            self.duck = snapshot.duck;
+        }
+        if let Some((gated, gate_sq)) = mv.gating() {
+            self.remove_piece(gate_sq);
+            self.add_to_pocket(self.side_to_move, gated);
+        }
+        let moving_piece = self.remove_piece(dst).expect("Missing destination");
+        let capture_sq = match kind {
+            MoveType::EnPassant => self.ep_capture_square(src, dst),
+            _ => dst,
+        };
+        
+        if self.holdings {
+            if let Some(victim) = captured {
+                let kind = if snapshot.captured_promoted { PieceKind::Pawn } else { victim.kind };
+                self.remove_from_pocket(moving_piece.color, kind);
+            }
+            let was_promoted = self.promoted.contains(dst) && kind != MoveType::Promotion;
+            self.promoted = self.promoted.clear(dst);
+            if was_promoted && kind != MoveType::Drop {
+                self.promoted = self.promoted.set(src);
+            }
+            if snapshot.captured_promoted {
+                self.promoted = self.promoted.set(capture_sq);
+            }
+        }
+        
+        match kind {
+            MoveType::Quiet | MoveType::Capture | MoveType::Teleport => {
                 self.set_piece(src, moving_piece);
                 if let Some(pc) = captured {
                     self.set_piece(dst, pc);
                 }
             }
             MoveType::Promotion => {
-                self.remove_piece(dst);
                 self.set_piece(src, Piece { kind: PieceKind::Pawn, color: moving_piece.color });
                 if let Some(pc) = captured {
                     self.set_piece(dst, pc);
@@ -297,31 +668,143 @@ impl Position {
             MoveType::EnPassant => {
                 self.set_piece(src, moving_piece);
                 if let Some(pc) = captured {
-                    self.set_piece(self.ep_square.unwrap(),pc);
-                    //self.set_piece(self.ep_capture_square(dst, moving_piece.color), pc);
+                    self.set_piece(capture_sq, pc);
                 }
             }
             MoveType::Castling => {
-                /*self.set_piece(src, moving_piece);
-                let (rook_src, rook_dst) = castling_rook_squares(src, dst);
-                let rook = self.remove_piece(rook_dst).unwrap();
-                self.set_piece(rook_src, rook);*/
+                let (rook_src, rook_dst) = self.castling_rook_squares(src, dst);
+                let rook = self.remove_piece(rook_dst);
+                self.set_piece(src, moving_piece);
+                if let Some(rook) = rook {
+                    self.set_piece(rook_src, rook);
+                }
+            }
+            MoveType::Drop => {
+                self.add_to_pocket(moving_piece.color, moving_piece.kind);
             }
         }
+        self.ep_square = snapshot.ep_square;
+        self.hash = snapshot.hash;
     }
     
-
+    fn default_castling_king_file(dims: &Dimensions) -> u8 {
+        if dims.width >= 8 { 4 } else { dims.width / 2 }
+    }
+    
+    /// For each color with a castling right, take the castling file from its king if
+    /// exactly one stands on its back rank, as on the f-file in Capablanca chess.
+    /// Other colors keep the default file.
+    pub fn detect_castling_king_files(&mut self) {
+        let rights = self.castling_rights;
+        for (color, has_right) in [
+            (Color::White, rights.has_white_kingside() || rights.has_white_queenside()),
+            (Color::Black, rights.has_black_kingside() || rights.has_black_queenside()),
+        ] {
+            let rank = self.back_rank(color);
+            let mut files = (0..self.dims.width).filter(|&file| {
+                let sq = Square::from_rank_file(rank, file, &self.dims);
+                self.piece_at(sq) == Some(Piece { color, kind: PieceKind::King })
+            });
+            let file = match (files.next(), files.next()) {
+                (Some(file), None) if has_right => file,
+                _ => Self::default_castling_king_file(&self.dims),
+            };
+            self.castling_king_files[color as usize] = file;
+        }
+    }
+    
+    fn back_rank(&self, color: Color) -> u8 {
+        match color {
+            Color::White => 0,
+            Color::Black => self.dims.height - 1,
+        }
+    }
+    
+    /// Home square of a king that may still castle, on `castling_king_files`
+    pub fn castling_king_square(&self, color: Color) -> Square {
+        Square::from_rank_file(self.back_rank(color), self.castling_king_files[color as usize], &self.dims)
+    }
+    
+    /// Rook origin and destination for a castling king move from `king_src` to `king_dst`.
+    /// The rook comes from the corner on the side the king moves towards and lands
+    /// on the square the king passed over.
+    pub fn castling_rook_squares(&self, king_src: Square, king_dst: Square) -> (Square, Square) {
+        let (src_file, rank) = king_src.file_rank(&self.dims);
+        let (dst_file, _) = king_dst.file_rank(&self.dims);
+        
+        if dst_file > src_file {
+            (
+                Square::from_rank_file(rank, self.dims.width - 1, &self.dims),
+                Square::from_rank_file(rank, dst_file - 1, &self.dims),
+            )
+        } else {
+            (
+                Square::from_rank_file(rank, 0, &self.dims),
+                Square::from_rank_file(rank, dst_file + 1, &self.dims),
+            )
+        }
+    }
+    
+    /// The en passant square if a pawn of the side to move stands next to it to take
+    /// there. Only then is it part of the hash, so a double push nobody can answer en
+    /// passant does not make the position differ from its repetitions.
+    pub(crate) fn capturable_ep_square(&self) -> Option<Square> {
+        let ep_sq = self.ep_square?;
+        let pawns = self.kind_bb(PieceKind::Pawn).intersect(self.color_bb(self.side_to_move));
+        let empty = B::empty_for_dims(&self.dims);
+        let takers = StandardPatterns::pawn_attacks(ep_sq, self.side_to_move.opposite(), &self.dims, empty);
+        (!takers.intersect(pawns).is_empty()).then_some(ep_sq)
+    }
+    
+    /// Square of the pawn removed by an en passant capture from `src` to `dst`
+    pub fn ep_capture_square(&self, src: Square, dst: Square) -> Square {
+        let (file, _) = dst.file_rank(&self.dims);
+        let (_, rank) = src.file_rank(&self.dims);
+        Square::from_rank_file(rank, file, &self.dims)
+    }
+    
+    /// Drop castling rights once a king moves or a corner rook moves or is captured
+    fn update_castling_rights(&mut self, src: Square, dst: Square, moving_piece: Piece) {
+        if moving_piece.kind == PieceKind::King {
+            match moving_piece.color {
+                Color::White => {
+                    self.castling_rights.set_white_kingside(false);
+                    self.castling_rights.set_white_queenside(false);
+                }
+                Color::Black => {
+                    self.castling_rights.set_black_kingside(false);
+                    self.castling_rights.set_black_queenside(false);
+                }
+            }
+        }
+        
+        let last_file = self.dims.width - 1;
+        let last_rank = self.dims.height - 1;
+        for sq in [src, dst] {
+            match sq.file_rank(&self.dims) {
+                (0, 0) => self.castling_rights.set_white_queenside(false),
+                (file, 0) if file == last_file => self.castling_rights.set_white_kingside(false),
+                (0, rank) if rank == last_rank => self.castling_rights.set_black_queenside(false),
+                (file, rank) if file == last_file && rank == last_rank => {
+                    self.castling_rights.set_black_kingside(false)
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::board::{Dimensions, BB};
+    use crate::board::{BitBoard64, Dimensions, BB};
+    use crate::movegen::MoveGenerator;
+    use crate::position::Fen;
 
     #[test]
     fn position_basics() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let sq = Square::from_rank_file(0, 0, &dims);
         pos.set_piece(sq, Piece {
@@ -338,7 +821,7 @@ mod tests {
     #[test]
     fn position_set_and_remove() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let sq = Square::from_rank_file(0, 0, &dims);
         let piece = Piece {
@@ -360,7 +843,7 @@ mod tests {
     #[test]
     fn position_switch_side() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         assert_eq!(pos.side_to_move, Color::White);
         pos.switch_side();
@@ -372,7 +855,7 @@ mod tests {
     #[test]
     fn position_piece_bb() {
         let dims = Dimensions::standard();
-        let mut pos = Position::new_empty(dims);
+        let mut pos: Position<BitBoard64> = Position::new_empty(dims);
         
         let sq1 = Square::from_rank_file(0, 0, &dims);
         let sq2 = Square::from_rank_file(0, 7, &dims);
@@ -391,4 +874,272 @@ mod tests {
         assert!(rooks.contains(sq1));
         assert!(rooks.contains(sq2));
     }
+    
+    fn find_move(pos: &Position, from: (u8, u8), to: (u8, u8)) -> Move {
+        let gen = MoveGenerator::new(pos.dims);
+        let src = Square::from_rank_file(from.1, from.0, &pos.dims);
+        let dst = Square::from_rank_file(to.1, to.0, &pos.dims);
+        gen.generate_legal(pos)
+            .into_iter()
+            .find(|m| m.src() == src && m.dst() == dst)
+            .expect("move not generated")
+    }
+    
+    #[test]
+    fn make_move_castling_moves_rook_and_revokes_rights() {
+        let dims = Dimensions::standard();
+        let mut pos = Fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", dims).unwrap();
+        
+        let castle = find_move(&pos, (4, 0), (6, 0));
+        assert_eq!(castle.kind(), MoveType::Castling);
+        pos.make_move(castle);
+        assert_eq!(Fen::to_string(&pos).unwrap(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        
+        pos.unmake_move(castle);
+        assert_eq!(Fen::to_string(&pos).unwrap(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    }
+    
+    #[test]
+    fn castling_follows_the_king_file_on_ten_files() {
+        let dims = Dimensions::new(10, 8);
+        let start = "r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1";
+        let mut pos = Fen::parse(start, dims).unwrap();
+        assert_eq!(pos.castling_king_files, [5, 5]);
+        
+        for (dst_file, fen) in [
+            (7, "r4k3r/10/10/10/10/10/10/R5RK2 b kq - 1 1"),
+            (3, "r4k3r/10/10/10/10/10/10/3KR4R b kq - 1 1"),
+        ] {
+            let castle = find_move(&pos, (5, 0), (dst_file, 0));
+            assert_eq!(castle.kind(), MoveType::Castling);
+            pos.make_move(castle);
+            assert_eq!(Fen::to_string(&pos).unwrap(), fen);
+            pos.unmake_move(castle);
+            assert_eq!(pos.try_make_move(castle), Ok(()));
+            pos.unmake_move(castle);
+        }
+        assert_eq!(Fen::to_string(&pos).unwrap(), start);
+    }
+    
+    #[test]
+    fn make_move_rook_capture_revokes_rights() {
+        let dims = Dimensions::standard();
+        let mut pos = Fen::parse("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", dims).unwrap();
+        
+        pos.make_move(find_move(&pos, (7, 0), (7, 7)));
+        assert_eq!(Fen::to_string(&pos).unwrap(), "r3k2R/8/8/8/8/8/8/R3K3 b Qq - 0 1");
+    }
+    
+    #[test]
+    fn make_move_double_push_sets_ep_square() {
+        let dims = Dimensions::standard();
+        let mut pos = Fen::parse("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", dims).unwrap();
+        
+        pos.make_move(find_move(&pos, (4, 1), (4, 3)));
+        assert_eq!(pos.ep_square, Some(Square::from_rank_file(2, 4, &dims)));
+        
+        let ep = find_move(&pos, (3, 3), (4, 2));
+        assert_eq!(ep.kind(), MoveType::EnPassant);
+        pos.make_move(ep);
+        assert_eq!(Fen::to_string(&pos).unwrap(), "4k3/8/8/8/8/4p3/8/4K3 w - - 0 2");
+        
+        pos.unmake_move(ep);
+        assert_eq!(Fen::to_string(&pos).unwrap(), "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+    }
+    
+    #[test]
+    fn make_move_underpromotion() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> = Fen::parse("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        
+        let b8 = Square::from_rank_file(7, 1, &dims);
+        let promo = gen.generate_legal(&pos)
+            .into_iter()
+            .find(|m| m.dst() == b8 && m.promotion_kind() == Some(PieceKind::Knight))
+            .unwrap();
+        pos.make_move(promo);
+        assert_eq!(Fen::to_string(&pos).unwrap(), "1N2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        
+        pos.unmake_move(promo);
+        assert_eq!(Fen::to_string(&pos).unwrap(), "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    }
+    
+    #[test]
+    fn holdings_capture_promote_and_drop() {
+        let dims = Dimensions::standard();
+        let start = "1r2k3/P2n4/8/8/8/8/8/4K3[] w - - 0 1";
+        let mut pos: Position<BitBoard64> = Fen::parse(start, dims).unwrap();
+        let mut played = Vec::new();
+        
+        for (uci, fen) in [
+            ("a7b8q", "1Q~2k3/3n4/8/8/8/8/8/4K3[R] b - - 0 1"),
+            // the promoted queen goes back to the pocket as a pawn
+            ("d7b8", "1n2k3/8/8/8/8/8/8/4K3[Rp] w - - 0 2"),
+            ("R@a1", "1n2k3/8/8/8/8/8/8/R3K3[p] b - - 1 2"),
+        ] {
+            let mv = Move::from_uci(uci, &pos).unwrap();
+            pos.make_move(mv);
+            played.push(mv);
+            assert_eq!(Fen::to_string(&pos).unwrap(), fen);
+            assert_eq!(pos.hash, Zobrist::compute(&pos), "{uci}");
+        }
+        assert_eq!(pos.pocket(Color::Black).count(PieceKind::Pawn), 1);
+        assert_eq!(pos.try_make_move(Move::new_drop(PieceKind::Knight, Square(20))), Err(MoveError::NotInPocket(PieceKind::Knight)));
+        
+        let full = format!("4k3/8/8/8/8/8/8/R3Kr2[{}] w - - 0 1", "R".repeat(255));
+        let mut crowded: Position<BitBoard64> = Fen::parse(&full, dims).unwrap();
+        let capture = Move::new(Square(4), Square(5), MoveType::Capture);
+        assert_eq!(crowded.try_make_move(capture), Err(MoveError::PocketFull(PieceKind::Rook)));
+        assert!(!crowded.add_to_pocket(Color::White, PieceKind::Rook));
+        
+        while let Some(mv) = played.pop() {
+            pos.unmake_move(mv);
+            assert_eq!(pos.hash, Zobrist::compute(&pos));
+        }
+        assert_eq!(Fen::to_string(&pos).unwrap(), start);
+    }
+
+    #[test]
+    fn duck_gating_and_teleport_make_and_unmake() {
+        let dims = Dimensions::standard();
+        let sq = |file, rank| Square::from_rank_file(rank, file, &dims);
+        let start = "4k3/8/8/3p4/8/8/8/4K1N1[Q] w - - 0 1";
+        let mut pos: Position<BitBoard64> = Fen::parse(start, dims).unwrap();
+        let knight = Move::new(sq(6, 0), sq(5, 2), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(knight.with_gating(PieceKind::Rook, sq(6, 0))), Err(MoveError::NotInPocket(PieceKind::Rook)));
+        assert_eq!(pos.try_make_move(knight.with_gating(PieceKind::Queen, sq(5, 2))), Err(MoveError::BlockedTarget(sq(5, 2).on(dims))));
+        let gated = knight.with_gating(PieceKind::Queen, sq(6, 0));
+
+        let mut played = Vec::new();
+        for (mv, fen) in [
+            (gated.with_duck(sq(4, 4)), "4k3/8/8/3p.3/8/5N2/8/4K1Q1[] b - - 1 1"),
+            (Move::new(sq(3, 4), sq(3, 3), MoveType::Quiet).with_duck(sq(0, 0)), "4k3/8/8/8/3p4/5N2/8/.3K1Q1[] w - - 0 2"),
+            (Move::new(sq(5, 2), sq(7, 7), MoveType::Teleport), "4k2N/8/8/8/3p4/8/8/.3K1Q1[] b - - 1 2"),
+        ] {
+            pos.try_make_move(mv).unwrap();
+            played.push(mv);
+            assert_eq!(Fen::to_string(&pos).unwrap(), fen);
+            assert_eq!(pos.hash, Zobrist::compute(&pos));
+        }
+        assert_eq!(pos.duck, Some(sq(0, 0)));
+
+        // the duck has to move, and cannot land where a piece or the gated piece ends up
+        let push = Move::new(sq(3, 3), sq(3, 2), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(push.with_duck(sq(0, 0))), Err(MoveError::BlockedTarget(sq(0, 0).on(dims))));
+        assert_eq!(pos.try_make_move(push.with_duck(sq(3, 2))), Err(MoveError::BlockedTarget(sq(3, 2).on(dims))));
+        assert_eq!(pos.try_make_move(push.with_duck(sq(7, 7))), Err(MoveError::BlockedTarget(sq(7, 7).on(dims))));
+        assert_eq!(pos.try_make_move(push.with_duck(sq(3, 3))), Ok(()));
+        played.push(push.with_duck(sq(3, 3)));
+
+        while let Some(mv) = played.pop() {
+            pos.unmake_move(mv);
+            assert_eq!(pos.hash, Zobrist::compute(&pos));
+        }
+        assert_eq!(pos.duck, None);
+        assert_eq!(Fen::to_string(&pos).unwrap(), start);
+    }
+
+    #[test]
+    fn make_unmake_long_game_restores_position() {
+        let boards = [
+            (Dimensions::standard(), "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1"),
+            (Dimensions::new(10, 10), "r3k4r/pppppppppp/10/10/10/10/10/10/PPPPPPPPPP/R3K4R w KQkq - 0 1"),
+        ];
+        
+        for (dims, fen) in boards {
+            let mut pos: Position = Fen::parse(fen, dims).unwrap();
+            let gen = MoveGenerator::new(dims);
+            let mut played = Vec::new();
+            let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+            
+            for _ in 0..300 {
+                let moves = gen.generate_legal(&pos);
+                if moves.is_empty() {
+                    break;
+                }
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let mv = moves[(seed % moves.len() as u64) as usize];
+                pos.make_move(mv);
+                played.push(mv);
+            }
+            
+            assert!(played.len() > 100);
+            while let Some(mv) = played.pop() {
+                pos.unmake_move(mv);
+            }
+            assert_eq!(Fen::to_string(&pos).unwrap(), fen);
+            assert!(pos.history.is_empty());
+        }
+    }
+    
+    #[test]
+    fn try_make_move_rejects_malformed_moves() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1", dims).unwrap();
+        let sq = |file, rank| Square::from_rank_file(rank, file, &dims);
+        
+        let empty = Move::new(sq(3, 3), sq(3, 4), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(empty), Err(MoveError::EmptySource(sq(3, 3).on(dims))));
+        assert_eq!(pos.try_make_move(empty).unwrap_err().to_string(), "no piece on source square d4");
+        
+        let black = Move::new(sq(4, 7), sq(4, 6), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(black), Err(MoveError::NotSideToMove(sq(4, 7).on(dims))));
+        
+        let onto_own = Move::new(sq(0, 0), sq(4, 0), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(onto_own), Err(MoveError::BlockedTarget(sq(4, 0).on(dims))));
+        assert_eq!(pos.try_make_move(onto_own).unwrap_err().to_string(), "target square e1 is blocked");
+        
+        let ep = Move::new(sq(4, 1), sq(3, 2), MoveType::EnPassant);
+        assert_eq!(pos.try_make_move(ep), Err(MoveError::KindMismatch(MoveType::EnPassant)));
+        
+        let rook_diagonal = Move::new(sq(0, 0), sq(1, 1), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(rook_diagonal), Err(MoveError::KindMismatch(MoveType::Quiet)));
+        
+        let early_promotion = Move::new_promotion(sq(4, 1), sq(4, 2), PieceKind::Queen);
+        assert_eq!(pos.try_make_move(early_promotion), Err(MoveError::KindMismatch(MoveType::Promotion)));
+        
+        let no_right = Move::new(sq(4, 0), sq(2, 0), MoveType::Castling);
+        assert_eq!(pos.try_make_move(no_right), Err(MoveError::KindMismatch(MoveType::Castling)));
+        
+        let off = Move::new(sq(0, 0), Square(64), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(off), Err(MoveError::OffBoard(Square(64))));
+        assert!(pos.history.is_empty());
+        
+        let push = Move::new(sq(4, 1), sq(4, 3), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(push), Ok(()));
+        let other = Move::new(sq(0, 0), sq(0, 5), MoveType::Quiet);
+        let err = pos.try_unmake_move(other).unwrap_err();
+        assert_eq!(err, MoveError::NotLastMove { mv: other, dims });
+        assert_eq!(err.to_string(), "move a1a6 was not the last move played");
+        assert_eq!(pos.try_unmake_move(push), Ok(()));
+        assert_eq!(pos.try_unmake_move(push), Err(MoveError::NothingToUndo));
+        
+        let mut pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/8/8/8/RN2K3 w Q - 0 1", dims).unwrap();
+        let blocked = Move::new(sq(4, 0), sq(2, 0), MoveType::Castling);
+        assert_eq!(pos.try_make_move(blocked), Err(MoveError::KindMismatch(MoveType::Castling)));
+        let knight_file = Move::new(sq(1, 0), sq(1, 7), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(knight_file), Err(MoveError::KindMismatch(MoveType::Quiet)));
+        let knight = Move::new(sq(1, 0), sq(2, 2), MoveType::Quiet);
+        assert_eq!(pos.try_make_move(knight), Ok(()));
+    }
+    
+    #[test]
+    fn try_new_empty_reports_size_errors() {
+        let too_big = Dimensions::new(10, 10);
+        assert_eq!(
+            Position::<BitBoard64>::try_new_empty(too_big).unwrap_err(),
+            DimensionsError::TooManySquares { squares: 100, max: 64 },
+        );
+        
+        let too_small = Dimensions { width: 4, height: 8 };
+        assert_eq!(
+            Position::<BitBoard64>::try_new_empty(too_small).unwrap_err(),
+            DimensionsError::OutOfRange { width: 4, height: 8 },
+        );
+        assert!(Dimensions::try_new(17, 8).is_err());
+        assert_eq!(Dimensions::try_new(16, 5), Ok(Dimensions { width: 16, height: 5 }));
+    }
 }
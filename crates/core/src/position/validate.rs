@@ -0,0 +1,245 @@
+use std::fmt;
+
+use crate::{
+    board::{BoardSquare, Square, BB},
+    movegen::MoveGenerator,
+    piece::{Color, Piece, PieceKind},
+    position::Position,
+};
+
+/// Something about a position that could not arise in play. Squares carry their board,
+/// so they display as coordinates such as `e4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionProblem {
+    /// A side has no king or more than one
+    KingCount { color: Color, count: u32 },
+    /// The side that just moved left its king attacked
+    OpponentInCheck { king: BoardSquare },
+    /// A pawn on its own first rank or on its promotion rank
+    PawnOnBackRank { sq: BoardSquare },
+    /// A piece of a custom kind the generator has no pattern or definition for
+    UnknownPiece { sq: BoardSquare, kind: PieceKind },
+    /// A castling right whose king is not on its home square, see
+    /// `Position::castling_king_files`
+    CastlingWithoutKing { color: Color, home: BoardSquare },
+    /// A castling right whose rook is not in its corner
+    CastlingWithoutRook { color: Color, kingside: bool },
+    /// The en passant square does not follow a double pawn push by the side that just moved
+    InvalidEnPassant { sq: BoardSquare },
+}
+
+impl fmt::Display for PositionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionProblem::KingCount { color, count } => write!(f, "{color:?} has {count} kings"),
+            PositionProblem::OpponentInCheck { king } => {
+                write!(f, "king on {king} is in check but not to move")
+            }
+            PositionProblem::PawnOnBackRank { sq } => write!(f, "pawn on {sq} is on a back rank"),
+            PositionProblem::UnknownPiece { sq, kind } => write!(f, "{kind:?} on {sq} has no move definition"),
+            PositionProblem::CastlingWithoutKing { color, home } => {
+                write!(f, "{color:?} may castle but its king is not on {home}")
+            }
+            PositionProblem::CastlingWithoutRook { color, kingside } => {
+                let side = if *kingside { "kingside" } else { "queenside" };
+                write!(f, "{color:?} may castle {side} but its rook is not in the corner")
+            }
+            PositionProblem::InvalidEnPassant { sq } => {
+                write!(f, "en passant square {sq} does not follow a double pawn push")
+            }
+        }
+    }
+}
+
+impl<B: BB> Position<B> {
+    /// Setup problems that `Fen::parse` and the editing methods let through, in a fixed
+    /// order. `gen` decides which pieces exist and how they attack, so custom pieces
+    /// and boards of any size are judged by the same rules as move generation. An
+    /// empty list means the position can be played.
+    pub fn validate(&self, gen: &MoveGenerator<B>) -> Vec<PositionProblem> {
+        let mut problems = Vec::new();
+
+        for color in [Color::White, Color::Black] {
+            let count = self.piece_bb(color, PieceKind::King).count();
+            if count != 1 {
+                problems.push(PositionProblem::KingCount { color, count });
+            }
+        }
+
+        let mut kings = self.piece_bb(self.side_to_move.opposite(), PieceKind::King);
+        while let Some(king) = kings.pop_lsb() {
+            if gen.is_square_attacked(self, king, self.side_to_move) {
+                problems.push(PositionProblem::OpponentInCheck { king: king.on(self.dims) });
+            }
+        }
+
+        let mut pawns = self.kind_bb(PieceKind::Pawn);
+        while let Some(sq) = pawns.pop_lsb() {
+            let (_, rank) = sq.file_rank(&self.dims);
+            if rank == 0 || rank == self.dims.height - 1 {
+                problems.push(PositionProblem::PawnOnBackRank { sq: sq.on(self.dims) });
+            }
+        }
+
+        for &kind in self.kinds() {
+            if matches!(kind, PieceKind::Custom(_)) && gen.registry().pattern(kind).is_none() {
+                let mut squares = self.kind_bb(kind);
+                while let Some(sq) = squares.pop_lsb() {
+                    problems.push(PositionProblem::UnknownPiece { sq: sq.on(self.dims), kind });
+                }
+            }
+        }
+
+        self.validate_castling(&mut problems);
+
+        if let Some(sq) = self.ep_square {
+            if !self.is_valid_ep_square(sq) {
+                problems.push(PositionProblem::InvalidEnPassant { sq: sq.on(self.dims) });
+            }
+        }
+        problems
+    }
+
+    fn validate_castling(&self, problems: &mut Vec<PositionProblem>) {
+        let rights = &self.castling_rights;
+        for (color, kingside, queenside) in [
+            (Color::White, rights.has_white_kingside(), rights.has_white_queenside()),
+            (Color::Black, rights.has_black_kingside(), rights.has_black_queenside()),
+        ] {
+            if !kingside && !queenside {
+                continue;
+            }
+            let king_sq = self.castling_king_square(color);
+            if self.piece_at(king_sq) != Some(Piece { color, kind: PieceKind::King }) {
+                problems.push(PositionProblem::CastlingWithoutKing { color, home: king_sq.on(self.dims) });
+            }
+
+            let (_, rank) = king_sq.file_rank(&self.dims);
+            let rook = Some(Piece { color, kind: PieceKind::Rook });
+            for (has_right, file, is_kingside) in [(kingside, self.dims.width - 1, true), (queenside, 0, false)] {
+                if has_right && self.piece_at(Square::from_rank_file(rank, file, &self.dims)) != rook {
+                    problems.push(PositionProblem::CastlingWithoutRook { color, kingside: is_kingside });
+                }
+            }
+        }
+    }
+
+    /// The square a pawn of the side that just moved skipped with a double push: on
+    /// its third rank, empty along with the square behind it, and with the pawn in front
+    fn is_valid_ep_square(&self, sq: Square) -> bool {
+        let mover = self.side_to_move.opposite();
+        let (file, rank) = sq.file_rank(&self.dims);
+        let (skipped_rank, origin_rank, pawn_rank) = match mover {
+            Color::White => (2, 1, 3),
+            Color::Black => match self.dims.height.checked_sub(3) {
+                Some(skipped) => (skipped, skipped + 1, skipped - 1),
+                None => return false,
+            },
+        };
+        if rank != skipped_rank {
+            return false;
+        }
+        let origin = Square::from_rank_file(origin_rank, file, &self.dims);
+        let pawn = Square::from_rank_file(pawn_rank, file, &self.dims);
+        !self.is_occupied(sq)
+            && !self.is_occupied(origin)
+            && self.piece_at(pawn) == Some(Piece { color: mover, kind: PieceKind::Pawn })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64, Dimensions};
+    use crate::movegen::patterns::{Direction, SlidingPattern};
+    use crate::position::Fen;
+
+    fn problems(fen: &str, dims: Dimensions) -> Vec<PositionProblem> {
+        let pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+        pos.validate(&MoveGenerator::new(dims))
+    }
+
+    #[test]
+    fn validate_accepts_legal_setups() {
+        let dims = Dimensions::standard();
+        assert_eq!(problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims), []);
+        assert_eq!(problems("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3", dims), []);
+        assert_eq!(problems("4k3/8/8/2pP4/8/8/8/4K3 w - c6 0 2", Dimensions::new(8, 8)), []);
+        assert_eq!(problems("4k/5/5/5/K4 w - - 0 1", Dimensions::new(5, 5)), []);
+    }
+
+    #[test]
+    fn validate_reports_each_problem() {
+        let dims = Dimensions::standard();
+        let sq = |file, rank| Square::from_rank_file(rank, file, &dims).on(dims);
+
+        assert_eq!(
+            problems("8/8/8/8/8/8/8/4K2K w - - 0 1", dims),
+            [
+                PositionProblem::KingCount { color: Color::White, count: 2 },
+                PositionProblem::KingCount { color: Color::Black, count: 0 },
+            ],
+        );
+        assert_eq!(
+            problems("P3k3/8/8/8/8/8/8/K3R2p w - - 0 1", dims),
+            [
+                PositionProblem::OpponentInCheck { king: sq(4, 7) },
+                PositionProblem::PawnOnBackRank { sq: sq(7, 0) },
+                PositionProblem::PawnOnBackRank { sq: sq(0, 7) },
+            ],
+        );
+        assert_eq!(
+            problems("r3k3/8/8/8/8/8/5K2/R6R w KQkq - 0 1", dims),
+            [
+                PositionProblem::CastlingWithoutKing { color: Color::White, home: sq(4, 0) },
+                PositionProblem::CastlingWithoutRook { color: Color::Black, kingside: true },
+            ],
+        );
+        assert_eq!(
+            problems("4k3/8/8/3pP3/8/8/8/4K3 w - e6 0 1", dims),
+            [PositionProblem::InvalidEnPassant { sq: sq(4, 5) }],
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/3pP3/8/8/4K3 w - e3 0 1", dims),
+            [PositionProblem::InvalidEnPassant { sq: sq(4, 2) }],
+        );
+        assert_eq!(
+            problems("P3k3/8/8/8/8/8/8/K7 w - - 0 1", dims)[0].to_string(),
+            "pawn on a8 is on a back rank",
+        );
+    }
+
+    #[test]
+    fn validate_castling_on_ten_files() {
+        let dims = Dimensions::new(10, 8);
+        let fen = "rnbbqkqnnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNBBQKQNNR w KQkq - 0 1";
+        let pos: Position<BitBoard256> = Fen::parse(fen, dims).unwrap();
+        assert_eq!(pos.validate(&MoveGenerator::new(dims)), []);
+
+        let pos: Position<BitBoard256> = Fen::parse("r4k3r/10/10/10/10/10/5K4/R8R w KQkq - 0 1", dims).unwrap();
+        let problems = pos.validate(&MoveGenerator::new(dims));
+        let home = Square::from_rank_file(0, 4, &dims).on(dims);
+        assert_eq!(problems, [PositionProblem::CastlingWithoutKing { color: Color::White, home }]);
+        assert_eq!(problems[0].to_string(), "White may castle but its king is not on e1");
+    }
+
+    #[test]
+    fn validate_uses_custom_patterns_on_large_boards() {
+        let dims = Dimensions::new(10, 10);
+        let mut pos: Position<BitBoard256> = Position::new_empty(dims);
+        let white_king = Square::from_rank_file(0, 0, &dims);
+        let black_king = Square::from_rank_file(9, 9, &dims);
+        pos.set_piece(white_king, Piece { color: Color::White, kind: PieceKind::King });
+        pos.set_piece(black_king, Piece { color: Color::Black, kind: PieceKind::King });
+        let custom_sq = Square::from_rank_file(5, 5, &dims);
+        pos.set_piece(custom_sq, Piece { color: Color::White, kind: PieceKind::Custom(2) });
+
+        let mut gen = MoveGenerator::new(dims);
+        let unknown = PositionProblem::UnknownPiece { sq: custom_sq.on(dims), kind: PieceKind::Custom(2) };
+        assert_eq!(pos.validate(&gen), [unknown]);
+        assert_eq!(unknown.to_string(), "Custom(2) on f6 has no move definition");
+
+        gen.register_custom_pattern(PieceKind::Custom(2), Box::new(SlidingPattern::new(Direction::BISHOP_DIRS.to_vec())));
+        assert_eq!(pos.validate(&gen), [PositionProblem::OpponentInCheck { king: black_king.on(dims) }]);
+    }
+}
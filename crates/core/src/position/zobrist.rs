@@ -0,0 +1,269 @@
+use crate::{
+    board::{Square, BB},
+    piece::{Color, Piece, PieceKind},
+    position::{CastlingRights, Position},
+};
+
+pub type ZobristKey = u64;
+
+/// Largest board supported by `Dimensions` (16x16)
+const MAX_SQUARES: usize = 256;
+const STANDARD_KINDS: usize = PieceKind::STANDARD_COUNT;
+
+/// Key domains, so piece, castling and en passant keys never share an input
+const PIECE_DOMAIN: u64 = 0;
+const SIDE_DOMAIN: u64 = 1 << 40;
+const CASTLING_DOMAIN: u64 = 2 << 40;
+const EN_PASSANT_DOMAIN: u64 = 3 << 40;
+const WALL_DOMAIN: u64 = 4 << 40;
+const POCKET_DOMAIN: u64 = 5 << 40;
+
+const SEED: u64 = 0x5374_6f6e_6b66_6973; // "Stonkfis"
+
+/// SplitMix64 finalizer. Keys are a pure function of their input, so they are
+/// identical across runs, builds and platforms.
+const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn piece_input(color: Color, kind: PieceKind, sq: u16) -> u64 {
+    let color_idx = match color {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    SEED ^ (PIECE_DOMAIN | ((kind.index() as u64) << 16) | (color_idx << 12) | sq as u64)
+}
+
+/// Precomputed keys for the standard pieces: [color][kind][square]
+static STANDARD_PIECE_KEYS: [[[ZobristKey; MAX_SQUARES]; STANDARD_KINDS]; 2] = {
+    const KINDS: [PieceKind; STANDARD_KINDS] = [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+        PieceKind::King,
+    ];
+    let mut keys = [[[0; MAX_SQUARES]; STANDARD_KINDS]; 2];
+    let mut color = 0;
+    while color < 2 {
+        let c = if color == 0 { Color::White } else { Color::Black };
+        let mut kind = 0;
+        while kind < STANDARD_KINDS {
+            let mut sq = 0;
+            while sq < MAX_SQUARES {
+                keys[color][kind][sq] = splitmix64(piece_input(c, KINDS[kind], sq as u16));
+                sq += 1;
+            }
+            kind += 1;
+        }
+        color += 1;
+    }
+    keys
+};
+
+/// Zobrist keys for `Position`.
+///
+/// The key covers piece placement (including `PieceKind::Custom` pieces), walls, pockets,
+/// side to move, castling rights and the en passant square when a pawn can take there, but
+/// not the move clocks, so transpositions share a key regardless of how they were reached.
+pub struct Zobrist;
+
+impl Zobrist {
+    #[inline]
+    pub fn piece(piece: Piece, sq: Square) -> ZobristKey {
+        match piece.kind {
+            PieceKind::Custom(_) => splitmix64(piece_input(piece.color, piece.kind, sq.0)),
+            kind => {
+                STANDARD_PIECE_KEYS[piece.color as usize][kind.index()][sq.idx() as usize]
+            }
+        }
+    }
+
+    /// Mixed in when black is to move
+    #[inline]
+    pub fn side_to_move() -> ZobristKey {
+        splitmix64(SEED ^ SIDE_DOMAIN)
+    }
+
+    #[inline]
+    pub fn castling(rights: CastlingRights) -> ZobristKey {
+        let bits = rights.as_u8();
+        let mut key = 0;
+        for bit in 0..4u64 {
+            if bits & (1 << bit) != 0 {
+                key ^= splitmix64(SEED ^ CASTLING_DOMAIN ^ bit);
+            }
+        }
+        key
+    }
+
+    #[inline]
+    pub fn en_passant(ep_square: Option<Square>) -> ZobristKey {
+        match ep_square {
+            Some(sq) => splitmix64(SEED ^ EN_PASSANT_DOMAIN ^ sq.0 as u64),
+            None => 0,
+        }
+    }
+
+    #[inline]
+    pub fn wall(sq: Square) -> ZobristKey {
+        splitmix64(SEED ^ WALL_DOMAIN ^ sq.0 as u64)
+    }
+
+    /// Key for holding `count` pieces of `kind` in hand; an empty slot has key 0, so
+    /// changing a count swaps the old key for the new one
+    #[inline]
+    pub fn pocket(color: Color, kind: PieceKind, count: u8) -> ZobristKey {
+        if count == 0 {
+            return 0;
+        }
+        let input = ((kind.index() as u64) << 16) | ((color as u64) << 12) | count as u64;
+        splitmix64(SEED ^ POCKET_DOMAIN ^ input)
+    }
+
+    /// Key of `pos` computed from scratch
+    pub fn compute<B: BB>(pos: &Position<B>) -> ZobristKey {
+        let mut key = 0;
+        let mut occupied = pos.all;
+        while let Some(sq) = occupied.pop_lsb() {
+            if let Some(piece) = pos.piece_at(sq) {
+                key ^= Self::piece(piece, sq);
+            }
+        }
+        let mut walls = pos.walls;
+        while let Some(sq) = walls.pop_lsb() {
+            key ^= Self::wall(sq);
+        }
+        for color in [Color::White, Color::Black] {
+            for (kind, count) in pos.pocket(color).iter() {
+                key ^= Self::pocket(color, kind, count);
+            }
+        }
+        if pos.side_to_move == Color::Black {
+            key ^= Self::side_to_move();
+        }
+        key ^ Self::castling(pos.castling_rights) ^ Self::en_passant(pos.capturable_ep_square())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64, Dimensions};
+    use crate::movegen::MoveGenerator;
+    use crate::position::Fen;
+
+    #[test]
+    fn zobrist_keys_are_deterministic() {
+        let dims = Dimensions::standard();
+        let fen_str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let a: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
+        let b: Position<BitBoard64> = Fen::parse(fen_str, dims).unwrap();
+
+        assert_eq!(a.hash, b.hash);
+        assert_eq!(a.hash, Zobrist::compute(&a));
+        // pinned so keys stored in books and tables stay valid between releases
+        assert_eq!(a.hash, 0xa540_5cd4_983d_37df);
+    }
+
+    #[test]
+    fn zobrist_transposition_shares_key() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", dims).unwrap();
+        let start = pos.hash;
+        let gen = MoveGenerator::new(dims);
+
+        // Nf3 Nf6 Ng1 Ng8
+        for (from, to) in [((6, 0), (5, 2)), ((6, 7), (5, 5)), ((5, 2), (6, 0)), ((5, 5), (6, 7))] {
+            let src = Square::from_rank_file(from.1, from.0, &dims);
+            let dst = Square::from_rank_file(to.1, to.0, &dims);
+            let mv = gen.generate_legal(&pos)
+                .into_iter()
+                .find(|m| m.src() == src && m.dst() == dst)
+                .unwrap();
+            pos.make_move(mv);
+        }
+
+        assert_eq!(pos.hash, start);
+        assert_ne!(pos.fullmove_number, 1);
+    }
+
+    #[test]
+    fn zobrist_incremental_matches_full_recompute() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> = Fen::parse("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", dims).unwrap();
+        let gen = MoveGenerator::new(dims);
+        let root = pos.hash;
+
+        for mv in gen.generate_legal(&pos) {
+            pos.make_move(mv);
+            assert_eq!(pos.hash, Zobrist::compute(&pos), "{}", mv.debug_string(&dims));
+            for reply in gen.generate_legal(&pos) {
+                pos.make_move(reply);
+                assert_eq!(pos.hash, Zobrist::compute(&pos));
+                pos.unmake_move(reply);
+            }
+            pos.unmake_move(mv);
+            assert_eq!(pos.hash, root);
+        }
+    }
+
+    #[test]
+    fn zobrist_hashes_en_passant_square_only_when_capturable() {
+        let dims = Dimensions::standard();
+        let gen = MoveGenerator::new(dims);
+        for (fen, without_ep, capturable) in [
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1", false),
+            ("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", "4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1", true),
+        ] {
+            let mut pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+            let push = gen.generate_legal(&pos).into_iter().find(|m| m.dst() == Square(28)).unwrap();
+            pos.make_move(push);
+            assert!(pos.ep_square.is_some());
+            assert_eq!(pos.hash, Zobrist::compute(&pos));
+            let plain: Position<BitBoard64> = Fen::parse(without_ep, dims).unwrap();
+            assert_eq!(pos.hash != plain.hash, capturable, "{fen}");
+        }
+    }
+
+    #[test]
+    fn zobrist_en_passant_key_follows_piece_edits() {
+        let dims = Dimensions::standard();
+        let mut pos: Position<BitBoard64> = Fen::parse("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1", dims).unwrap();
+        let d4 = Square::parse("d4", &dims).unwrap();
+        let without_taker = pos.hash;
+
+        pos.set_piece(d4, Piece { color: Color::Black, kind: PieceKind::Pawn });
+        assert_eq!(pos.hash, Zobrist::compute(&pos));
+        pos.set_piece(d4, Piece { color: Color::Black, kind: PieceKind::Knight });
+        assert_eq!(pos.hash, Zobrist::compute(&pos));
+        pos.set_piece(d4, Piece { color: Color::Black, kind: PieceKind::Pawn });
+        pos.switch_side();
+        assert_eq!(pos.hash, Zobrist::compute(&pos));
+        pos.switch_side();
+        pos.remove_piece(d4);
+        assert_eq!(pos.hash, without_taker);
+    }
+
+    #[test]
+    fn zobrist_custom_pieces_on_16x16() {
+        let dims = Dimensions::new(16, 16);
+        let mut pos: Position<BitBoard256> = Position::new_empty(dims);
+        let corner = Square::from_rank_file(15, 15, &dims);
+        let a = Piece { color: Color::White, kind: PieceKind::Custom(3) };
+        let b = Piece { color: Color::White, kind: PieceKind::Custom(4) };
+
+        pos.set_piece(corner, a);
+        let with_a = pos.hash;
+        pos.set_piece(corner, b);
+        assert_ne!(pos.hash, with_a);
+        assert_eq!(pos.hash, Zobrist::compute(&pos));
+
+        pos.remove_piece(corner);
+        assert_eq!(pos.hash, 0);
+    }
+}
@@ -0,0 +1,347 @@
+//! Serde support, enabled by the `serde` feature.
+//!
+//! The JSON shape is part of the public API and stays the same when internal
+//! representations such as bitboards or the packed `Move` change:
+//!
+//! - `Dimensions`: `{"width": 8, "height": 8}`
+//! - `Square`: the square index, `0` for a1 and counting along each rank
+//! - `Color`: `"white"` or `"black"`
+//! - `PieceKind`: `"pawn"`, `"knight"`, `"bishop"`, `"rook"`, `"queen"`, `"king"` or `{"custom": 3}`
+//! - `Piece`: `{"color": "white", "kind": "knight"}`
+//! - `Move`: `{"from": 12, "to": 28, "type": "quiet"}`, where `type` is one of `quiet`,
+//!   `capture`, `promotion`, `en_passant`, `castling`, `drop` or `teleport`. Promotions
+//!   and drops add the piece kind as `"piece"`; a drop has `from` equal to `to`. The
+//!   optional fields `"captured"` (a kind), `"duck"` (a square) and `"gating"`
+//!   (`{"kind": "knight", "square": 1}`) are left out when unset.
+//! - `CastlingRights`: `{"white_kingside": true, "white_queenside": true,
+//!   "black_kingside": false, "black_queenside": false}`
+//! - `Position`:
+//!
+//! ```json
+//! {
+//!   "dims": {"width": 8, "height": 8},
+//!   "side_to_move": "white",
+//!   "pieces": [{"square": 4, "color": "white", "kind": "king"}],
+//!   "walls": [],
+//!   "duck": null,
+//!   "pockets": {"white": [{"kind": "knight", "count": 1}], "black": []},
+//!   "holdings": true,
+//!   "promoted": [],
+//!   "castling_rights": {"white_kingside": false, "white_queenside": false, "black_kingside": false, "black_queenside": false},
+//!   "ep_square": null,
+//!   "halfmove_clock": 0,
+//!   "fullmove_number": 1
+//! }
+//! ```
+//!
+//! Pieces are listed in square order, and the duck also appears among the walls. The
+//! move history is not written, so a deserialized position cannot unmake the moves
+//! that led to it, and the Zobrist key is recomputed on load.
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    board::{Dimensions, Square, BB},
+    moves::{Move, MoveType},
+    piece::{Color, Piece, PieceKind},
+    position::{CastlingRights, Position},
+};
+
+#[derive(Serialize, Deserialize)]
+struct MoveRepr {
+    from: Square,
+    to: Square,
+    #[serde(rename = "type")]
+    move_type: MoveType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    piece: Option<PieceKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    captured: Option<PieceKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duck: Option<Square>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gating: Option<Gating>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Gating {
+    kind: PieceKind,
+    square: Square,
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoveRepr {
+            from: self.src(),
+            to: self.dst(),
+            move_type: self.kind(),
+            piece: self.promotion_kind().or(self.drop_kind()),
+            captured: self.captured(),
+            duck: self.duck_square(),
+            gating: self.gating().map(|(kind, square)| Gating { kind, square }),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MoveRepr::deserialize(deserializer)?;
+        // the packed fields hold squares up to 255, the last one of a 16x16 board
+        let squares = [("from", Some(repr.from)), ("to", Some(repr.to)), ("duck", repr.duck)]
+            .into_iter()
+            .chain([("gating.square", repr.gating.as_ref().map(|gating| gating.square))]);
+        for (field, sq) in squares {
+            if let Some(sq) = sq.filter(|sq| sq.0 > u8::MAX as u16) {
+                return Err(D::Error::custom(format!("{field} square {} is off every board", sq.0)));
+            }
+        }
+        let mut mv = match (repr.move_type, repr.piece) {
+            (MoveType::Promotion, Some(kind)) => Move::new_promotion(repr.from, repr.to, kind),
+            (MoveType::Promotion, None) => Move::new_promotion(repr.from, repr.to, PieceKind::Queen),
+            (MoveType::Drop, Some(kind)) => Move::new_drop(kind, repr.to),
+            (MoveType::Drop, None) => return Err(D::Error::missing_field("piece")),
+            (move_type, None) => Move::new(repr.from, repr.to, move_type),
+            (move_type, Some(_)) => return Err(D::Error::custom(format!("{move_type:?} move cannot name a piece"))),
+        };
+        if let Some(kind) = repr.captured {
+            mv = mv.with_captured(kind);
+        }
+        if let Some(sq) = repr.duck {
+            mv = mv.with_duck(sq);
+        }
+        if let Some(gating) = repr.gating {
+            mv = mv.with_gating(gating.kind, gating.square);
+        }
+        Ok(mv)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CastlingRightsRepr {
+    white_kingside: bool,
+    white_queenside: bool,
+    black_kingside: bool,
+    black_queenside: bool,
+}
+
+impl Serialize for CastlingRights {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CastlingRightsRepr {
+            white_kingside: self.has_white_kingside(),
+            white_queenside: self.has_white_queenside(),
+            black_kingside: self.has_black_kingside(),
+            black_queenside: self.has_black_queenside(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CastlingRights {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = CastlingRightsRepr::deserialize(deserializer)?;
+        let mut rights = CastlingRights::new();
+        rights.set_white_kingside(repr.white_kingside);
+        rights.set_white_queenside(repr.white_queenside);
+        rights.set_black_kingside(repr.black_kingside);
+        rights.set_black_queenside(repr.black_queenside);
+        Ok(rights)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlacedPiece {
+    square: Square,
+    #[serde(flatten)]
+    piece: Piece,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PocketEntry {
+    kind: PieceKind,
+    count: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PocketsRepr {
+    white: Vec<PocketEntry>,
+    black: Vec<PocketEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PositionRepr {
+    dims: Dimensions,
+    side_to_move: Color,
+    pieces: Vec<PlacedPiece>,
+    #[serde(default)]
+    walls: Vec<Square>,
+    #[serde(default)]
+    duck: Option<Square>,
+    #[serde(default)]
+    pockets: Option<PocketsRepr>,
+    #[serde(default)]
+    holdings: bool,
+    #[serde(default)]
+    promoted: Vec<Square>,
+    castling_rights: CastlingRights,
+    ep_square: Option<Square>,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+}
+
+fn squares<B: BB>(mut bb: B) -> Vec<Square> {
+    std::iter::from_fn(|| bb.pop_lsb()).collect()
+}
+
+impl<B: BB> Serialize for Position<B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let pocket = |color| {
+            self.pocket(color)
+                .iter()
+                .map(|(kind, count)| PocketEntry { kind, count })
+                .collect()
+        };
+        PositionRepr {
+            dims: self.dims,
+            side_to_move: self.side_to_move,
+            pieces: squares(self.all)
+                .into_iter()
+                .filter_map(|square| Some(PlacedPiece { square, piece: self.piece_at(square)? }))
+                .collect(),
+            walls: squares(self.walls),
+            duck: self.duck,
+            pockets: Some(PocketsRepr { white: pocket(Color::White), black: pocket(Color::Black) }),
+            holdings: self.holdings,
+            promoted: squares(self.promoted),
+            castling_rights: self.castling_rights,
+            ep_square: self.ep_square,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, B: BB> Deserialize<'de> for Position<B> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PositionRepr::deserialize(deserializer)?;
+        let mut pos = Position::<B>::try_new_empty(repr.dims).map_err(D::Error::custom)?;
+        let check = |sq: Square| {
+            if sq.0 < repr.dims.num_squares() {
+                Ok(sq)
+            } else {
+                Err(D::Error::custom(format!("square {} is off the {}x{} board", sq.0, repr.dims.width, repr.dims.height)))
+            }
+        };
+
+        for sq in repr.walls {
+            pos.set_wall(check(sq)?);
+        }
+        if let Some(sq) = repr.duck {
+            if !pos.is_wall(check(sq)?) {
+                return Err(D::Error::custom(format!("duck square {} is not among the walls", sq.0)));
+            }
+            pos.duck = Some(sq);
+        }
+        for placed in repr.pieces {
+            let sq = check(placed.square)?;
+            if pos.is_wall(sq) || pos.is_occupied(sq) {
+                return Err(D::Error::custom(format!("square {} holds more than one piece or wall", sq.0)));
+            }
+            pos.set_piece(sq, placed.piece);
+        }
+        for (color, entries) in repr.pockets.into_iter().flat_map(|p| [(Color::White, p.white), (Color::Black, p.black)]) {
+            for entry in entries {
+                for _ in 0..entry.count {
+                    if !pos.add_to_pocket(color, entry.kind) {
+                        return Err(D::Error::custom(format!("more than {} {:?} in a pocket", u8::MAX, entry.kind)));
+                    }
+                }
+            }
+        }
+        for sq in repr.promoted {
+            pos.promoted = pos.promoted.set(check(sq)?);
+        }
+        pos.holdings = repr.holdings;
+        pos.side_to_move = repr.side_to_move;
+        pos.castling_rights = repr.castling_rights;
+        pos.ep_square = repr.ep_square.map(check).transpose()?;
+        pos.halfmove_clock = repr.halfmove_clock;
+        pos.fullmove_number = repr.fullmove_number;
+        pos.detect_castling_king_files();
+        pos.refresh_hash();
+        Ok(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BitBoard256, BitBoard64};
+    use crate::position::Fen;
+
+    #[test]
+    fn serde_value_types_json_shape() {
+        let dims = Dimensions::new(10, 10);
+        let mv = Move::new_promotion(Square(81), Square(91), PieceKind::Custom(2));
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(json, r#"{"from":81,"to":91,"type":"promotion","piece":{"custom":2}}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+
+        let duck = Move::new(Square(12), Square(28), MoveType::Capture).with_captured(PieceKind::Pawn).with_duck(Square(255));
+        let json = serde_json::to_string(&duck).unwrap();
+        assert_eq!(json, r#"{"from":12,"to":28,"type":"capture","captured":"pawn","duck":255}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), duck);
+
+        let drop = Move::new_drop(PieceKind::Knight, Square(44));
+        assert_eq!(serde_json::to_string(&drop).unwrap(), r#"{"from":44,"to":44,"type":"drop","piece":"knight"}"#);
+        let castle = Move::new(Square(4), Square(6), MoveType::Castling);
+        assert_eq!(serde_json::from_str::<Move>(r#"{"from":4,"to":6,"type":"castling"}"#).unwrap(), castle);
+        assert!(serde_json::from_str::<Move>(r#"{"from":4,"to":6,"type":"quiet","piece":"queen"}"#).is_err());
+        for json in [
+            r#"{"from":300,"to":5,"type":"quiet"}"#,
+            r#"{"from":5,"to":256,"type":"quiet"}"#,
+            r#"{"from":4,"to":5,"type":"quiet","duck":600}"#,
+            r#"{"from":4,"to":5,"type":"quiet","gating":{"kind":"knight","square":256}}"#,
+        ] {
+            let err = serde_json::from_str::<Move>(json).unwrap_err();
+            assert!(err.to_string().contains("is off every board"), "{json}: {err}");
+        }
+
+        assert_eq!(serde_json::to_string(&dims).unwrap(), r#"{"width":10,"height":10}"#);
+        let piece = Piece { color: Color::Black, kind: PieceKind::Queen };
+        assert_eq!(serde_json::to_string(&piece).unwrap(), r#"{"color":"black","kind":"queen"}"#);
+    }
+
+    #[test]
+    fn serde_position_round_trip() {
+        let dims = Dimensions::standard();
+        let fen = "r3k2r/8/8/3pP3/8/8/8/R3K2R[Qn] w Kq d6 0 12";
+        let mut pos: Position<BitBoard64> = Fen::parse(fen, dims).unwrap();
+        pos.set_wall(Square(27));
+        let json = serde_json::to_value(&pos).unwrap();
+        assert_eq!(json["side_to_move"], "white");
+        assert_eq!(json["pieces"][0], serde_json::json!({"square": 0, "color": "white", "kind": "rook"}));
+        assert_eq!(json["pockets"]["black"], serde_json::json!([{"kind": "knight", "count": 1}]));
+        assert_eq!(json["castling_rights"]["black_queenside"], true);
+        assert_eq!(json["ep_square"], 43);
+
+        let back: Position<BitBoard64> = serde_json::from_value(json).unwrap();
+        assert_eq!(Fen::to_string(&back).unwrap(), Fen::to_string(&pos).unwrap());
+        assert!(back.is_wall(Square(27)));
+        assert_eq!(back.hash, pos.hash);
+    }
+
+    #[test]
+    fn serde_position_rejects_bad_input() {
+        let parse = |json: &str| serde_json::from_str::<Position<BitBoard64>>(json);
+        let base = r#""side_to_move":"white","castling_rights":{"white_kingside":false,"white_queenside":false,"black_kingside":false,"black_queenside":false},"ep_square":null,"halfmove_clock":0,"fullmove_number":1"#;
+        assert!(parse(&format!(r#"{{"dims":{{"width":8,"height":8}},"pieces":[],{base}}}"#)).is_ok());
+        assert!(parse(&format!(r#"{{"dims":{{"width":10,"height":10}},"pieces":[],{base}}}"#)).is_err());
+        assert!(parse(&format!(r#"{{"dims":{{"width":8,"height":8}},"pieces":[{{"square":64,"color":"white","kind":"king"}}],{base}}}"#)).is_err());
+        assert!(serde_json::from_str::<Position<BitBoard256>>(&format!(r#"{{"dims":{{"width":10,"height":10}},"pieces":[],{base}}}"#)).is_ok());
+        let knights = r#"{"kind":"knight","count":200}"#;
+        let err = parse(&format!(r#"{{"dims":{{"width":8,"height":8}},"pieces":[],"pockets":{{"white":[{knights},{knights}],"black":[]}},{base}}}"#)).unwrap_err();
+        assert!(err.to_string().contains("more than 255 Knight in a pocket"), "{err}");
+    }
+}